@@ -2,12 +2,23 @@ use serde::{Serialize, Deserialize};
 
 use crate::{
     model::{
+        application_error::{ApplicationError, ErrorCode},
         application_model::ApplicationModel,
-        input::script_event::ScriptEvent
+        input::{
+            axis_map::AxisMap,
+            button_map::PhysicalButton,
+            gamepad::{GamepadButton, GamepadType, MergePolicy},
+            input_filter::InputFilter,
+            macro_binding::MacroBinding,
+            script_event::ScriptEvent,
+            turbo::TurboMode
+        }
     },
-    controller::stdio_interface::StdioInterface
+    controller::{
+        application_response::ApplicationResponse,
+        client_session::ClientSession
+    }
 };
-use serde_json::json;
 
 #[derive(Clone, Serialize, Deserialize)]
 /// Commands accepted by this application.
@@ -15,7 +26,8 @@ use serde_json::json;
 /// This application accepts these commands through the controller, which reads
 /// from stdin. As long as you correctly serialize these commands as JSON and
 /// pass them through stdin, the controller should be able to parse them down
-/// into their respective enumerations.
+/// into their respective enumerations. See CommandEnvelope for how to tag them
+/// with an ID.
 pub enum ApplicationCommand {
     GetAnarchyMode,
     SetAnarchyMode { anarchy_mode: bool },
@@ -27,8 +39,80 @@ pub enum ApplicationCommand {
     SetLeftDeadzone { i: usize, deadzone: f32 },
     GetRightDeadzone { i: usize },
     SetRightDeadzone { i: usize, deadzone: f32 },
+    // Changing the type also resets the button map to that type's defaults.
+    SetGamepadType {
+        i: usize,
+        #[serde(rename = "type")]
+        gamepad_type: GamepadType
+    },
+    GetButtonMap { i: usize },
+    // Mapping a button to nothing unmaps it, and mapping it to more than one
+    // button presses all of them. Triggers can be mapped here too.
+    SetButtonMapping { i: usize, from: PhysicalButton, to: Vec<GamepadButton> },
+    ResetButtonMap { i: usize },
+    // Anything left out of the axis map goes back to its default.
+    GetAxisMap { i: usize },
+    SetAxisMap { i: usize, axis_map: AxisMap },
+    // A turbo button stays pressed for the given number of frames, then
+    // released for as many. At 60 Hz, 3 frames fires 10 times a second.
+    GetTurbo { i: usize },
+    SetTurbo {
+        i: usize,
+        button: GamepadButton,
+        mode: TurboMode,
+        frames: usize
+    },
+    ClearTurbo { i: usize, button: GamepadButton },
+    // Merging moves every controller in slot j into slot i, so they all drive
+    // the same gamepad. Unmerging gives them their own slots back. Either side
+    // can be filtered down to part of its controllers, like "Sticks" on one
+    // and "Buttons" on the other, and both default to "All".
+    MergeGamepads {
+        i: usize,
+        j: usize,
+        #[serde(default)]
+        i_filter: InputFilter,
+        #[serde(default)]
+        j_filter: InputFilter
+    },
+    UnmergeGamepads { i: usize },
+    // Splitting gives the left half of the controller in slot i to slot i as
+    // a sideways left Joy-Con, and its right half to slot j as a sideways
+    // right Joy-Con. Unsplitting (on slot i) gives it all back to slot i.
+    SplitGamepad { i: usize, j: usize },
+    UnsplitGamepad { i: usize },
+    GetMergePolicy { i: usize },
+    SetMergePolicy { i: usize, policy: MergePolicy },
+    // Profiles are kept in the config file, under [profiles.<name>].
+    ListProfiles,
+    SaveConfig { profile: String },
+    LoadConfig { profile: String },
 
+    // Running a script responds with the handle it goes by, which is what
+    // cancelling it takes. Cancelling lets go of every button and stick it
+    // touched.
     RunScript { i: usize, script: Vec<ScriptEvent> },
+    // Scripts can also be written as text, like "press A 3f; wait 10f". See
+    // script_text::parse for everything it can do.
+    RunScriptText { i: usize, text: String },
+    ParseScriptText { text: String },
+    FormatScriptText { script: Vec<ScriptEvent> },
+    // Scripts are kept in the config file too, under [scripts.<name>].
+    ListScripts,
+    GetScript { name: String },
+    SaveScript { name: String, script: Vec<ScriptEvent> },
+    DeleteScript { name: String },
+    RunNamedScript { i: usize, name: String },
+    ListRunningScripts,
+    CancelScript { handle: usize },
+    // A macro runs its script whenever every button in its trigger is held
+    // on that slot. Setting one replaces whichever had the same trigger.
+    ListMacros { i: usize },
+    SetMacro { i: usize, binding: MacroBinding },
+    ClearMacro { i: usize, trigger: Vec<PhysicalButton> },
+    // Periodically sends the state of every gamepad as a GamepadStates event.
+    Subscribe { rate_hz: f32 },
+    Unsubscribe,
     Connect,
     Disconnect,
     Exit,
@@ -40,148 +124,172 @@ pub enum ApplicationCommand {
     Unsupported
 }
 
-fn write(stdio_if: &mut StdioInterface, result: Result<String, String>) -> () {
-    stdio_if.write(json!(result).to_string())
-        .expect("Failed to write to stdout buffer.");
-}
-
 impl ApplicationCommand {
     pub fn execute(
-        self, model: &mut ApplicationModel, stdio_if: &mut StdioInterface
-    ) -> () {
-        let ok: bool;
-        let out: String;
+        self, model: &mut ApplicationModel, session: &mut ClientSession
+    ) -> Result<ApplicationResponse, ApplicationError> {
         match self {
-            Self::GetAnarchyMode => {
-                match model.get_anarchy_mode() {
-                    Err(e) => { ok = false; out = e },
-                    Ok(anarchy_mode) => {
-                        ok = true;
-                        out = anarchy_mode.to_string()
-                    }
-                }
-            },
-            Self::SetAnarchyMode { anarchy_mode } => {
-                match model.set_anarchy_mode(anarchy_mode) {
-                    Err(e) => { ok = false; out = e },
-                    Ok(_) => {
-                        ok = true;
-                        out = String::from("Successfully set anarchy mode.")
-                    }
+            Self::GetAnarchyMode => model.get_anarchy_mode()
+                .map(|anarchy_mode| ApplicationResponse::AnarchyMode {
+                    anarchy_mode: anarchy_mode
+                }),
+            Self::SetAnarchyMode { anarchy_mode } => model
+                .set_anarchy_mode(anarchy_mode)
+                .map(|_| ApplicationResponse::Ack),
+            Self::GetIps => model.get_ips()
+                .map(|ips| ApplicationResponse::Ips { ips: ips }),
+            Self::SetIps { ips } => model.set_ips(ips)
+                .map(|_| ApplicationResponse::Ack),
+            Self::GetDelay { i } => model.get_delay(i)
+                .map(|delay| ApplicationResponse::Delay { i: i, delay: delay }),
+            Self::SetDelay { i, delay } => model.set_delay(i, delay)
+                .map(|_| ApplicationResponse::Ack),
+            Self::GetLeftDeadzone { i } => model.get_left_deadzone(i)
+                .map(|deadzone| ApplicationResponse::LeftDeadzone {
+                    i: i,
+                    deadzone: deadzone
+                }),
+            Self::SetLeftDeadzone { i, deadzone } => model
+                .set_left_deadzone(i, deadzone)
+                .map(|_| ApplicationResponse::Ack),
+            Self::GetRightDeadzone { i } => model.get_right_deadzone(i)
+                .map(|deadzone| ApplicationResponse::RightDeadzone {
+                    i: i,
+                    deadzone: deadzone
+                }),
+            Self::SetRightDeadzone { i, deadzone } => model
+                .set_right_deadzone(i, deadzone)
+                .map(|_| ApplicationResponse::Ack),
+            Self::SetGamepadType { i, gamepad_type } => model
+                .set_gamepad_type(i, gamepad_type)
+                .map(|_| ApplicationResponse::Ack),
+            Self::GetButtonMap { i } => model.get_button_map(i)
+                .map(|button_map| ApplicationResponse::ButtonMap {
+                    i: i,
+                    mappings: button_map.get_mappings()
+                }),
+            Self::SetButtonMapping { i, from, to } => model
+                .set_button_mapping(i, from, to)
+                .map(|_| ApplicationResponse::Ack),
+            Self::ResetButtonMap { i } => model.reset_button_map(i)
+                .map(|_| ApplicationResponse::Ack),
+            Self::GetAxisMap { i } => model.get_axis_map(i)
+                .map(|axis_map| ApplicationResponse::AxisMap {
+                    i: i,
+                    axis_map: axis_map
+                }),
+            Self::SetAxisMap { i, axis_map } => model.set_axis_map(i, axis_map)
+                .map(|_| ApplicationResponse::Ack),
+            Self::GetTurbo { i } => model.get_turbo(i)
+                .map(|buttons| ApplicationResponse::Turbo {
+                    i: i,
+                    buttons: buttons
+                }),
+            Self::SetTurbo { i, button, mode, frames } => model
+                .set_turbo(i, button, Some((mode, frames)))
+                .map(|_| ApplicationResponse::Ack),
+            Self::ClearTurbo { i, button } => model.set_turbo(i, button, None)
+                .map(|_| ApplicationResponse::Ack),
+            Self::MergeGamepads { i, j, i_filter, j_filter } => model
+                .merge_gamepads(i, j, i_filter, j_filter)
+                .map(|_| ApplicationResponse::Ack),
+            Self::UnmergeGamepads { i } => model.unmerge_gamepads(i)
+                .map(|_| ApplicationResponse::Ack),
+            Self::SplitGamepad { i, j } => model.split_gamepad(i, j)
+                .map(|_| ApplicationResponse::Ack),
+            Self::UnsplitGamepad { i } => model.unsplit_gamepad(i)
+                .map(|_| ApplicationResponse::Ack),
+            Self::GetMergePolicy { i } => model.get_merge_policy(i)
+                .map(|policy| ApplicationResponse::MergePolicy {
+                    i: i,
+                    policy: policy
+                }),
+            Self::SetMergePolicy { i, policy } => model
+                .set_merge_policy(i, policy)
+                .map(|_| ApplicationResponse::Ack),
+            Self::ListProfiles => model.list_profiles()
+                .map(|profiles| ApplicationResponse::Profiles {
+                    profiles: profiles
+                }),
+            Self::SaveConfig { profile } => model.save_profile(&profile)
+                .map(|_| ApplicationResponse::Ack),
+            Self::LoadConfig { profile } => model.load_profile(&profile)
+                .map(|_| ApplicationResponse::Ack),
+            Self::RunScript { i, script } => model.run_script(i, script)
+                .map(|handle| ApplicationResponse::ScriptStarted {
+                    i: i,
+                    handle: handle
+                }),
+            Self::RunScriptText { i, text } => model.run_script_text(i, &text)
+                .map(|handle| ApplicationResponse::ScriptStarted {
+                    i: i,
+                    handle: handle
+                }),
+            Self::ParseScriptText { text } => model.parse_script_text(&text)
+                .map(|script| ApplicationResponse::ParsedScript {
+                    script: script
+                }),
+            Self::FormatScriptText { script } => model
+                .format_script_text(&script)
+                .map(|text| ApplicationResponse::ScriptText { text: text }),
+            Self::ListScripts => model.list_scripts()
+                .map(|names| ApplicationResponse::Scripts { names: names }),
+            Self::GetScript { name } => model.get_script(&name)
+                .map(|script| ApplicationResponse::Script {
+                    name: name,
+                    script: script
+                }),
+            Self::SaveScript { name, script } => model
+                .save_script(&name, &script)
+                .map(|_| ApplicationResponse::Ack),
+            Self::DeleteScript { name } => model.delete_script(&name)
+                .map(|_| ApplicationResponse::Ack),
+            Self::RunNamedScript { i, name } => model
+                .run_named_script(i, &name)
+                .map(|handle| ApplicationResponse::ScriptStarted {
+                    i: i,
+                    handle: handle
+                }),
+            Self::ListRunningScripts => model.get_running_scripts()
+                .map(|scripts| ApplicationResponse::RunningScripts {
+                    scripts: scripts
+                }),
+            Self::CancelScript { handle } => model.cancel_script(handle)
+                .map(|_| ApplicationResponse::Ack),
+            Self::ListMacros { i } => model.get_macros(i)
+                .map(|macros| ApplicationResponse::Macros {
+                    i: i,
+                    macros: macros
+                }),
+            Self::SetMacro { i, binding } => model.set_macro(i, binding)
+                .map(|_| ApplicationResponse::Ack),
+            Self::ClearMacro { i, trigger } => model.clear_macro(i, trigger)
+                .map(|_| ApplicationResponse::Ack),
+            Self::Subscribe { rate_hz } => {
+                if rate_hz.is_finite() && rate_hz > 0.0 {
+                    session.subscribe(rate_hz);
+                    Ok(ApplicationResponse::Ack)
+                } else {
+                    Err(ApplicationError::new(
+                        ErrorCode::InvalidArgument,
+                        "Subscription rate must be a positive number."))
                 }
             },
-            Self::GetIps => match model.get_ips() {
-                Err(e) => { ok = false; out = e },
-                Ok(ips) => { ok = true; out = json!(ips).to_string() } 
-            },
-            Self::SetIps { ips } => match model.set_ips(ips) {
-                Err(e) => { ok = false; out = e },
-                Ok(_) => {
-                    ok = true;
-                    out = String::from("Successfully set IPs.");
-                }
-            },
-            Self::GetDelay { i } => {
-                match model.get_delay(i) {
-                    Err(e) => { ok = false; out = e },
-                    Ok(delay) => { ok = true; out = delay.to_string() }
-                }
-            },
-            Self::SetDelay { i, delay } => {
-                match model.set_delay(i, delay) { 
-                    Err(e) => { ok = false; out = e },
-                    Ok(_) => {
-                        ok = true;
-                        out = format!(
-                            "Successfully set delay of gamepad {}.", i)
-                    }
-                }
-            },
-            Self::GetLeftDeadzone { i } => {
-                match model.get_left_deadzone(i) {
-                    Err(e) => { ok = false; out = e },
-                    Ok(deadzone) => {
-                        ok = true;
-                        out = deadzone.to_string()
-                    }
-                }
-            },
-            Self::SetLeftDeadzone {
-                i, deadzone
-            } => match model.set_left_deadzone(i, deadzone) {
-                Err(e) => { ok = false; out = e },
-                Ok(_) => {
-                    ok = true;
-                    out = format!(
-                        "Successfully set left deadzone of gamepad {}.", i)
-                }
-            },
-            Self::GetRightDeadzone { i } => {
-                match model.get_right_deadzone(i) {
-                    Err(e) => { ok = false; out = e },
-                    Ok(deadzone) => {
-                        ok = true;
-                        out = deadzone.to_string()
-                    }
-                }
-            },
-            Self::SetRightDeadzone {
-                i, deadzone
-            } => match model.set_right_deadzone(i, deadzone) {
-                Err(e) => { ok = false; out = e },
-                Ok(_) => {
-                    ok = true;
-                    out = format!(
-                        "Successfully set right deadzone of gamepad {}.", i)
-                }
-            },
-            Self::RunScript {
-                i, script
-            } => match model.run_script(i, script) {
-                Err(e) => { ok = false; out = e },
-                Ok(_) => {
-                    ok = true;
-                    out = format!(
-                        "Successfully ran script for gamepad {}.", i)
-                }
-            },
-            Self::Connect => match model.connect() {
-                Err(e) => { ok = false; out = e },
-                Ok(_) => {
-                    ok = true;
-                    out = String::from("Now sending packets.")
-                }
-            },
-            Self::Disconnect => match model.disconnect() {
-                Err(e) => { ok = false; out = e },
-                Ok(_) => {
-                    ok = true;
-                    out = String::from("No longer sending packets.")
-                }
-            },
-            Self::Exit => match model.exit() {
-                Err(e) => { ok = false; out = e },
-                Ok(_) => {
-                    ok = true;
-                    out = String::from("Successfully exited.")
-                }
-            },
-            Self::Swap { i, j } => match model.swap(i, j) {
-                Err(e) => { ok = false; out = e },
-                Ok(_) => {
-                    ok = true;
-                    out = format!(
-                        "Successfully swapped gamepads {} and {}.", i, j)
-                }
+            Self::Unsubscribe => {
+                session.unsubscribe();
+                Ok(ApplicationResponse::Ack)
             },
-            Self::Unsupported => {
-                ok = false;
-                out = String::from("The given command is unsupported.")
-            }
+            Self::Connect => model.connect()
+                .map(|_| ApplicationResponse::Ack),
+            Self::Disconnect => model.disconnect()
+                .map(|_| ApplicationResponse::Ack),
+            Self::Exit => model.exit()
+                .map(|_| ApplicationResponse::Ack),
+            Self::Swap { i, j } => model.swap(i, j)
+                .map(|_| ApplicationResponse::Ack),
+            Self::Unsupported => Err(ApplicationError::new(
+                ErrorCode::UnsupportedCommand,
+                "The given command is unsupported."))
         }
-        write(stdio_if, match ok {
-            false => Err(out),
-            true => Ok(out)
-        });
     }
 }
@@ -0,0 +1,178 @@
+use std::{fs, path::{Path, PathBuf}, time::Duration};
+
+use crate::{
+    model::{
+        input_source::InputConfig,
+        keyboard_mouse::KeyboardMouseConfig,
+        remote_interface::RemoteConfig
+    },
+    controller::command_transport::TransportConfig
+};
+#[cfg(target_os = "linux")]
+use crate::model::input_source::INPUT_DIRECTORY;
+use serde::{Serialize, Deserialize};
+
+/// Where the config file is expected to be if one isn't given.
+pub const DEFAULT_CONFIG_PATH: &str = "./vhid_companion.toml";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+/// Everything that can be configured before this application starts.
+///
+/// These can be loaded from a TOML file, and anything left out of it just
+/// falls back to its default.
+pub struct ApplicationOptions {
+    /// Whether to read input from the SDL event server.
+    pub sdl_server: bool,
+    /// Where to find the SDL event server.
+    pub server_path: PathBuf,
+    /// Whether to read gamepads straight from /dev/input, which only works on
+    /// Linux.
+    pub evdev: bool,
+    /// If set, a keyboard and mouse act as another gamepad. Also Linux only.
+    pub keyboard_mouse: Option<KeyboardMouseConfig>,
+    /// Where remote players can send their input to, if anywhere.
+    pub remote: RemoteConfig,
+    /// The address that the UDP socket for the sysmodule is bound to.
+    pub bind: String,
+    /// The port that the sysmodule listens on.
+    pub port: u16,
+    /// How many times per second everything updates. Delays and script waits
+    /// are counted in these updates, so changing this changes how long they
+    /// take too.
+    pub rate: u32,
+    pub ips: Vec<String>,
+    /// Whether to start sending packets as soon as we start.
+    pub connect: bool,
+    /// Whether to ignore stdio, for when nothing's driving us through it.
+    pub headless: bool,
+
+    pub tcp: Option<u16>,
+    pub unix: Option<PathBuf>,
+    pub websocket: Option<u16>,
+
+    /// The config file that these were loaded from, which is also where
+    /// profiles get saved to.
+    #[serde(skip)]
+    pub config_path: PathBuf,
+    /// A profile from the config file to load on startup.
+    pub profile: Option<String>
+}
+
+impl Default for ApplicationOptions {
+    fn default() -> ApplicationOptions {
+        return ApplicationOptions {
+            sdl_server: true,
+            server_path: PathBuf::from("./sdl_event_server"),
+            evdev: false,
+            keyboard_mouse: None,
+            remote: RemoteConfig::default(),
+            bind: String::from("0.0.0.0:8000"),
+            port: 8000,
+            rate: 60,
+            ips: vec!(),
+            connect: false,
+            headless: false,
+
+            tcp: None,
+            unix: None,
+            websocket: None,
+
+            config_path: PathBuf::from(DEFAULT_CONFIG_PATH),
+            profile: None
+        }
+    }
+}
+
+impl ApplicationOptions {
+    pub fn load(path: &Path) -> Result<ApplicationOptions, String> {
+        let mut options: ApplicationOptions = match fs::read_to_string(path) {
+            Err(e) => return Err(
+                format!("Failed to read config file: {}", e)),
+            Ok(contents) => toml::from_str(&contents)
+                .map_err(|e| format!("Failed to parse config file: {}", e))?
+        };
+        options.config_path = path.to_path_buf();
+        return Ok(options);
+    }
+
+    /// Checks for anything that these options ask for but can't have.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.rate == 0 {
+            return Err(String::from("The update rate must be at least 1."));
+        }
+        if cfg!(not(target_os = "linux")) {
+            if self.evdev {
+                return Err(String::from(
+                    "Reading from evdev is only supported on Linux."));
+            }
+            if self.keyboard_mouse.is_some() {
+                return Err(String::from(
+                    "Keyboard and mouse input is only supported on Linux."));
+            }
+        }
+        if cfg!(not(unix)) && self.unix.is_some() {
+            return Err(String::from(
+                "Unix sockets are only supported on Unix-like platforms."));
+        }
+        return Ok(());
+    }
+
+    /// Returns how long a single update takes.
+    pub fn get_frame(&self) -> Duration {
+        return Duration::from_secs_f64(1.0 / self.rate as f64);
+    }
+
+    /// Returns every input source that these options ask for.
+    pub fn get_inputs(&self) -> Vec<InputConfig> {
+        let mut inputs: Vec<InputConfig> = vec!();
+        if self.sdl_server {
+            inputs.push(InputConfig::SdlServer {
+                path: self.server_path.clone()
+            });
+        }
+        // Options that ask for these anywhere else don't pass validation.
+        #[cfg(target_os = "linux")]
+        if self.evdev {
+            inputs.push(InputConfig::Evdev {
+                directory: PathBuf::from(INPUT_DIRECTORY)
+            });
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(config) = &self.keyboard_mouse {
+            inputs.push(InputConfig::KeyboardMouse {
+                directory: PathBuf::from(INPUT_DIRECTORY),
+                config: config.clone()
+            });
+        }
+        if self.remote.tcp.is_some() || self.remote.udp.is_some() {
+            inputs.push(InputConfig::Remote { config: self.remote.clone() });
+        }
+        return inputs;
+    }
+
+    /// Returns every transport that these options ask for.
+    ///
+    /// The TCP and WebSocket transports only ever bind to localhost, since
+    /// there's no authentication on either.
+    pub fn get_transports(&self) -> Vec<TransportConfig> {
+        let mut transports: Vec<TransportConfig> = vec!();
+        if !self.headless {
+            transports.push(TransportConfig::Stdio);
+        }
+        if let Some(port) = self.tcp {
+            transports.push(TransportConfig::Tcp {
+                address: format!("127.0.0.1:{}", port)
+            });
+        }
+        if let Some(path) = &self.unix {
+            transports.push(TransportConfig::Unix { path: path.clone() });
+        }
+        if let Some(port) = self.websocket {
+            transports.push(TransportConfig::WebSocket {
+                address: format!("127.0.0.1:{}", port)
+            });
+        }
+        return transports;
+    }
+}
@@ -0,0 +1,37 @@
+use crate::model::input::{
+    axis_map::AxisMap,
+    button_map::ButtonMapping,
+    gamepad::MergePolicy,
+    macro_binding::MacroBinding,
+    script_event::ScriptEvent,
+    script_status::ScriptStatus,
+    turbo::TurboButton
+};
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Serialize, Deserialize)]
+/// Responses returned by this application after executing a command.
+///
+/// These are serialized the same way as ApplicationCommand, so a response with
+/// a payload looks like `{"Delay": {"i": 0, "delay": 3}}`, while commands that
+/// only need confirming just get `"Ack"`.
+pub enum ApplicationResponse {
+    AnarchyMode { anarchy_mode: bool },
+    Ips { ips: Vec<String> },
+    Delay { i: usize, delay: usize },
+    LeftDeadzone { i: usize, deadzone: f32 },
+    RightDeadzone { i: usize, deadzone: f32 },
+    ButtonMap { i: usize, mappings: Vec<ButtonMapping> },
+    AxisMap { i: usize, axis_map: AxisMap },
+    MergePolicy { i: usize, policy: MergePolicy },
+    Turbo { i: usize, buttons: Vec<TurboButton> },
+    Macros { i: usize, macros: Vec<MacroBinding> },
+    Profiles { profiles: Vec<String> },
+    Scripts { names: Vec<String> },
+    Script { name: String, script: Vec<ScriptEvent> },
+    ParsedScript { script: Vec<ScriptEvent> },
+    ScriptText { text: String },
+    ScriptStarted { i: usize, handle: usize },
+    RunningScripts { scripts: Vec<ScriptStatus> },
+    Ack
+}
@@ -0,0 +1,79 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering}
+    }
+};
+
+use crate::{
+    model::{
+        application_event::ApplicationEvent,
+        input_source::{InputConfig, InputSource},
+        remote_client::{RemoteClientConfig, RemoteClientModel}
+    },
+    controller::application_options::ApplicationOptions
+};
+use crossbeam_channel::{tick, select, Receiver};
+
+fn report_events(model: &RemoteClientModel) -> () {
+    for event in model.take_events() {
+        if let ApplicationEvent::ServerExited = event {
+            eprintln!("The SDL server exited.");
+        }
+    }
+}
+
+/// Runs this application as a remote player of another companion, rather than
+/// as a companion of its own.
+///
+/// There's nothing to command here, so this just keeps going until the SDL
+/// server exits or we're killed.
+pub struct ClientController {
+    client_thread: thread::JoinHandle<()>
+}
+
+impl ClientController {
+    pub fn new(
+        options: ApplicationOptions, config: RemoteClientConfig
+    ) -> Result<ClientController, String> {
+        let frame: Duration = options.get_frame();
+        let ticks: Receiver<Instant> = tick(frame);
+        let done: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+
+        let mut sources: Vec<Box<dyn InputSource>> = vec!();
+        for input in options.get_inputs() {
+            // Players of ours would have nowhere to go.
+            if let InputConfig::Remote { config: _ } = input {
+                continue;
+            }
+            sources.push(
+                input.into_source(ticks.clone(), frame, Arc::clone(&done)));
+        }
+        let mut model: RemoteClientModel = RemoteClientModel::new(
+            ticks.clone(), Arc::clone(&done), sources, config)?;
+
+        let thread_done: Arc<AtomicBool> = Arc::clone(&done);
+        let client_thread: thread::JoinHandle<()> = thread::spawn(move || {
+            while !thread_done.load(Ordering::Relaxed) {
+                select! {
+                    recv(ticks) -> _ => report_events(&model)
+                }
+            }
+            // We might've stopped because of the server exiting.
+            report_events(&model);
+            if let Err(e) = model.exit() {
+                eprintln!("{}", e);
+            }
+        });
+
+        return Ok(ClientController {
+            client_thread: client_thread
+        });
+    }
+
+    pub fn join(self) -> () {
+        self.client_thread.join().expect("Failed to join client thread.");
+    }
+}
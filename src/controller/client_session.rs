@@ -0,0 +1,40 @@
+use std::time::{Duration, Instant};
+
+/// State that belongs to whoever is sending commands, rather than to the model.
+pub struct ClientSession {
+    snapshot_interval: Option<Duration>,
+    last_snapshot: Instant
+}
+
+impl ClientSession {
+    pub fn new() -> ClientSession {
+        return ClientSession {
+            snapshot_interval: None,
+            last_snapshot: Instant::now()
+        }
+    }
+
+    pub fn subscribe(&mut self, rate_hz: f32) -> () {
+        self.snapshot_interval = Some(Duration::from_secs_f32(1.0 / rate_hz));
+        self.last_snapshot = Instant::now();
+    }
+
+    pub fn unsubscribe(&mut self) -> () {
+        self.snapshot_interval = None;
+    }
+
+    /// Returns whether it's time to send this client another snapshot, and
+    /// starts waiting for the next one if it is.
+    ///
+    /// Since this is only checked once per tick, rates higher than the tick
+    /// rate effectively get capped to it.
+    pub fn snapshot_due(&mut self, now: Instant) -> bool {
+        if let Some(interval) = self.snapshot_interval {
+            if now.duration_since(self.last_snapshot) >= interval {
+                self.last_snapshot = now;
+                return true;
+            }
+        }
+        return false;
+    }
+}
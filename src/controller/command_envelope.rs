@@ -0,0 +1,95 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    model::{
+        application_error::{ApplicationError, ErrorCode},
+        application_event::ApplicationEvent
+    },
+    controller::{
+        application_command::ApplicationCommand,
+        application_response::ApplicationResponse
+    }
+};
+
+/// A command read from a client, along with the ID the client sent it with.
+///
+/// Clients are expected to wrap their commands like so:
+/// `{"id": 1, "command": {"GetDelay": {"i": 0}}}`. Any JSON value can be used
+/// as an ID, and it'll be echoed back as-is in the response. Bare commands
+/// without an envelope are still accepted, but their responses will have a
+/// null ID.
+pub struct CommandEnvelope {
+    pub id: Option<Value>,
+    pub command: Result<ApplicationCommand, ApplicationError>
+}
+
+impl CommandEnvelope {
+    pub fn parse(line: &str) -> CommandEnvelope {
+        let value: Value = match serde_json::from_str(line) {
+            Err(e) => return CommandEnvelope {
+                id: None,
+                command: Err(ApplicationError::new(
+                    ErrorCode::ParseError,
+                    &format!("Failed to parse line as JSON: {}", e)))
+            },
+            Ok(value) => value
+        };
+        // Value.get() just returns None if the value isn't an object, so this
+        // is fine for bare unit commands like "GetIps" too.
+        let id: Option<Value> = value.get("id").cloned();
+        let maybe_command: serde_json::Result<ApplicationCommand>
+            = match value.get("command") {
+                None => serde_json::from_value(value),
+                Some(command) => serde_json::from_value(command.clone())
+            };
+        return CommandEnvelope {
+            id: id,
+            command: maybe_command.map_err(|e| ApplicationError::new(
+                ErrorCode::ParseError,
+                &format!("Failed to parse command: {}", e)))
+        }
+    }
+}
+
+#[derive(Serialize)]
+/// A response to a command, tagged with the ID of the command it's for.
+pub struct ResponseEnvelope {
+    id: Option<Value>,
+    result: Result<ApplicationResponse, ApplicationError>
+}
+
+impl ResponseEnvelope {
+    pub fn new(
+        id: Option<Value>,
+        result: Result<ApplicationResponse, ApplicationError>
+    ) -> ResponseEnvelope {
+        return ResponseEnvelope {
+            id: id,
+            result: result
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        return serde_json::json!(self).to_string();
+    }
+}
+
+#[derive(Serialize)]
+/// An event that wasn't asked for by any command.
+///
+/// These look like `{"event": {"ControllerAdded": {"i": 0}}}`, so they can be
+/// told apart from responses by their lack of an ID.
+pub struct EventEnvelope {
+    event: ApplicationEvent
+}
+
+impl EventEnvelope {
+    pub fn new(event: ApplicationEvent) -> EventEnvelope {
+        return EventEnvelope { event: event }
+    }
+
+    pub fn to_json(&self) -> String {
+        return serde_json::json!(self).to_string();
+    }
+}
@@ -1,58 +1,193 @@
 use std::{
-    time::Instant,
+    collections::HashMap,
+    time::{Duration, Instant},
     sync::{
-        Arc, Mutex,
+        Arc,
         atomic::AtomicBool
     }
 };
 
 use crate::{
-    model::application_model::ApplicationModel,
+    model::{
+        application_error::ApplicationError,
+        application_event::ApplicationEvent,
+        application_model::ApplicationModel,
+        input_source::InputSource
+    },
     controller::{
-        application_command::ApplicationCommand,
-        stdio_interface::StdioInterface
+        application_options::ApplicationOptions,
+        application_response::ApplicationResponse,
+        client_session::ClientSession,
+        command_envelope::{CommandEnvelope, EventEnvelope, ResponseEnvelope},
+        command_transport::{CommandTransport, TransportConfig},
+        socket_interface::SocketInterface,
+        stdio_interface::StdioInterface,
+        websocket_interface::WebSocketInterface
     }
 };
 use crossbeam_channel::Receiver;
 
+/// Identifies a client by the index of its transport and its ID within it.
+type ClientKey = (usize, usize);
+
 pub struct CommandReader {
     model: ApplicationModel,
-    stdio_if: StdioInterface
+    transports: Vec<Box<dyn CommandTransport>>,
+    sessions: HashMap<ClientKey, ClientSession>
 }
 
 impl CommandReader {
     pub fn new(
         ticks: Receiver<Instant>, done: Arc<AtomicBool>,
-        commands_mtx: Arc<Mutex<Vec<ApplicationCommand>>>
-    ) -> CommandReader {
+        options: &ApplicationOptions
+    ) -> Result<CommandReader, String> {
+        // These go first, so one that can't bind doesn't leave the input
+        // sources running behind it.
+        let mut transports: Vec<Box<dyn CommandTransport>> = vec!();
+        for config in options.get_transports() {
+            let transport_ticks: Receiver<Instant> = ticks.clone();
+            let transport_done: Arc<AtomicBool> = Arc::clone(&done);
+            transports.push(match config {
+                TransportConfig::Stdio => Box::new(
+                    StdioInterface::new(transport_ticks, transport_done)),
+                TransportConfig::Tcp { address } => Box::new(
+                    SocketInterface::bind_tcp(
+                        &address, transport_ticks, transport_done)?),
+                #[cfg(unix)]
+                TransportConfig::Unix { path } => Box::new(
+                    SocketInterface::bind_unix(
+                        &path, transport_ticks, transport_done)?),
+                // Options validation doesn't let these through.
+                #[cfg(not(unix))]
+                TransportConfig::Unix { path: _ } => return Err(String::from(
+                    "Unix sockets aren't supported on this platform.")),
+                TransportConfig::WebSocket { address } => Box::new(
+                    WebSocketInterface::new(
+                        &address, transport_ticks, transport_done)?)
+            });
+        }
+
         let model_ticks: Receiver<Instant> = ticks.clone();
         let model_done: Arc<AtomicBool> = Arc::clone(&done);
-        let model: ApplicationModel = ApplicationModel::new(
+        let frame: Duration = options.get_frame();
+        let mut sources: Vec<Box<dyn InputSource>> = vec!();
+        for config in options.get_inputs() {
+            sources.push(
+                config.into_source(ticks.clone(), frame, Arc::clone(&done)));
+        }
+        let mut model: ApplicationModel = ApplicationModel::new(
             model_ticks,
-            model_done);
-
-        let stdio_ticks: Receiver<Instant> = ticks.clone();
-        let stdio_done: Arc<AtomicBool> = Arc::clone(&done);
-        let stdio_mtx: Arc<Mutex<Vec<ApplicationCommand>>>
-            = Arc::clone(&commands_mtx);
+            model_done,
+            sources,
+            &options.bind,
+            options.port,
+            &options.config_path,
+            frame)?;
+        // Flags and options take priority over the profile, so it goes first.
+        if let Some(profile) = &options.profile {
+            if let Err(e) = model.load_profile(profile) {
+                eprintln!("{}", e.get_message());
+            }
+        }
+        if options.ips.len() != 0 {
+            if let Err(e) = model.set_ips(options.ips.clone()) {
+                eprintln!("{}", e.get_message());
+            }
+        }
+        if options.connect {
+            if let Err(e) = model.connect() {
+                eprintln!("{}", e.get_message());
+            }
+        }
 
-        return CommandReader {
+        return Ok(CommandReader {
             model: model,
-            stdio_if: StdioInterface::new(stdio_ticks, stdio_done, stdio_mtx)
+            transports: transports,
+            sessions: HashMap::new()
+        });
+    }
+
+    pub fn update(&mut self) -> () {
+        let mut received: Vec<(ClientKey, CommandEnvelope)> = vec!();
+        for (t, transport) in self.transports.iter_mut().enumerate() {
+            let mut requests: Vec<(usize, CommandEnvelope)> = vec!();
+            transport.read_into(&mut requests);
+            for (client, envelope) in requests {
+                received.push(((t, client), envelope));
+            }
+        }
+        for (key, envelope) in received {
+            self.accept(key, envelope);
         }
+        self.write_events();
+        self.write_snapshots();
+        self.forget_sessions();
     }
 
-    pub fn update(
-        &mut self, commands_mtx: &Arc<Mutex<Vec<ApplicationCommand>>>
-    ) -> () {
-        if let Ok(mut commands) = commands_mtx.lock() {
-            while let Some(command) = commands.pop() {
-                self.accept(command);
+    /// Writes out any events that the model has picked up on its own, to every
+    /// client of every transport.
+    pub fn write_events(&mut self) -> () {
+        for event in self.model.take_events() {
+            let s: String = EventEnvelope::new(event).to_json();
+            for t in 0..self.transports.len() {
+                for client in self.transports[t].get_clients() {
+                    self.write((t, client), s.clone());
+                }
             }
         }
     }
 
-    pub fn accept(&mut self, command: ApplicationCommand) -> () {
-        command.execute(&mut self.model, &mut self.stdio_if);
+    fn write_snapshots(&mut self) -> () {
+        let now: Instant = Instant::now();
+        let mut due: Vec<ClientKey> = vec!();
+        for (key, session) in self.sessions.iter_mut() {
+            if session.snapshot_due(now) {
+                due.push(*key);
+            }
+        }
+        if due.len() == 0 {
+            return;
+        }
+        if let Ok(gamepads) = self.model.get_snapshots() {
+            let event: ApplicationEvent = ApplicationEvent::GamepadStates {
+                gamepads: gamepads
+            };
+            let s: String = EventEnvelope::new(event).to_json();
+            for key in due {
+                self.write(key, s.clone());
+            }
+        }
+    }
+
+    /// Drops the sessions of clients that have gone away.
+    fn forget_sessions(&mut self) -> () {
+        let mut remaining: Vec<ClientKey> = vec!();
+        for (t, transport) in self.transports.iter().enumerate() {
+            for client in transport.get_clients() {
+                remaining.push((t, client));
+            }
+        }
+        self.sessions.retain(|key, _| remaining.contains(key));
+    }
+
+    fn write(&mut self, key: ClientKey, s: String) -> () {
+        let (t, client): ClientKey = key;
+        if let Err(e) = self.transports[t].write(client, s) {
+            eprintln!("{}", e);
+        }
+    }
+
+    pub fn accept(&mut self, key: ClientKey, envelope: CommandEnvelope) -> () {
+        let session: &mut ClientSession = self.sessions
+            .entry(key)
+            .or_insert_with(ClientSession::new);
+        let result: Result<ApplicationResponse, ApplicationError>
+            = match envelope.command {
+                Err(e) => Err(e),
+                Ok(command) => command.execute(&mut self.model, session)
+            };
+        let response: ResponseEnvelope = ResponseEnvelope::new(
+            envelope.id, result);
+        self.write(key, response.to_json());
     }
 }
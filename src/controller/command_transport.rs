@@ -0,0 +1,41 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex}
+};
+
+use crate::controller::command_envelope::CommandEnvelope;
+
+/// The transports that commands can be sent through.
+///
+/// Any number of these can be active at once, and every one of them gets the
+/// same events.
+#[derive(Clone)]
+pub enum TransportConfig {
+    Stdio,
+    Tcp { address: String },
+    Unix { path: PathBuf },
+    WebSocket { address: String }
+}
+
+/// Commands that a transport's threads have received, but that haven't been
+/// handed off to the command reader yet.
+pub type RequestsMutex = Arc<Mutex<Vec<(usize, CommandEnvelope)>>>;
+
+/// Something that clients can send commands through and get replies back from.
+///
+/// Each transport can have any number of clients, which it identifies however
+/// it likes, so long as the IDs stay the same for as long as a client is
+/// around.
+pub trait CommandTransport: Send {
+    /// Moves every command received since the last call into the given
+    /// vector, along with the ID of the client that sent it.
+    fn read_into(
+        &mut self, requests: &mut Vec<(usize, CommandEnvelope)>
+    ) -> ();
+
+    /// Writes a single line to one of this transport's clients.
+    fn write(&mut self, client: usize, s: String) -> Result<(), String>;
+
+    /// Returns the IDs of every client that's still around.
+    fn get_clients(&self) -> Vec<usize>;
+}
@@ -1,4 +1,12 @@
 pub mod application_command;
 pub mod application_controller;
+pub mod application_options;
+pub mod application_response;
+pub mod client_controller;
+pub mod client_session;
+pub mod command_envelope;
 pub mod command_reader;
+pub mod command_transport;
+pub mod socket_interface;
 pub mod stdio_interface;
+pub mod websocket_interface;
@@ -0,0 +1,278 @@
+use std::{
+    collections::HashMap,
+    io,
+    io::{BufReader, BufRead, Write},
+    marker::PhantomData,
+    net::{Shutdown, TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering}
+    }
+};
+#[cfg(unix)]
+use std::{
+    fs,
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream}
+    },
+    path::Path
+};
+
+use crate::controller::{
+    command_envelope::CommandEnvelope,
+    command_transport::{CommandTransport, RequestsMutex}
+};
+use crossbeam_channel::{select, unbounded, Receiver, Sender};
+
+/// How long a client gets to take a reply or event off our hands before we
+/// give up on it, so one that stops reading can't pile them up forever.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+type ClientsMutex = Arc<Mutex<HashMap<usize, Sender<String>>>>;
+
+/// Streams that a SocketInterface can hand out to its clients.
+pub trait SocketStream: io::Read + Write + Send + Sized + 'static {
+    fn try_clone_stream(&self) -> io::Result<Self>;
+    fn set_blocking(&self) -> io::Result<()>;
+    fn set_write_timeout_to(&self, timeout: Duration) -> io::Result<()>;
+    fn shutdown_stream(&self) -> ();
+}
+
+impl SocketStream for TcpStream {
+    fn try_clone_stream(&self) -> io::Result<TcpStream> {
+        return self.try_clone();
+    }
+
+    fn set_blocking(&self) -> io::Result<()> {
+        return self.set_nonblocking(false);
+    }
+
+    fn set_write_timeout_to(&self, timeout: Duration) -> io::Result<()> {
+        return self.set_write_timeout(Some(timeout));
+    }
+
+    fn shutdown_stream(&self) -> () {
+        let _ = self.shutdown(Shutdown::Both);
+    }
+}
+
+#[cfg(unix)]
+impl SocketStream for UnixStream {
+    fn try_clone_stream(&self) -> io::Result<UnixStream> {
+        return self.try_clone();
+    }
+
+    fn set_blocking(&self) -> io::Result<()> {
+        return self.set_nonblocking(false);
+    }
+
+    fn set_write_timeout_to(&self, timeout: Duration) -> io::Result<()> {
+        return self.set_write_timeout(Some(timeout));
+    }
+
+    fn shutdown_stream(&self) -> () {
+        let _ = self.shutdown(Shutdown::Both);
+    }
+}
+
+/// Sends a client everything that's written to it, one line at a time, until
+/// it disconnects or stops keeping up. Either way, the connection gets shut
+/// down, which stops its reader too.
+fn spawn_client_writer<S: SocketStream>(
+    id: usize, mut stream: S, outgoing: Receiver<String>,
+    clients_mtx: ClientsMutex
+) -> () {
+    thread::spawn(move || {
+        for s in outgoing.iter() {
+            let mut line: String = s;
+            line.push('\n');
+            let result: io::Result<()> = stream.write_all(line.as_bytes())
+                .and_then(|_| stream.flush());
+            if let Err(_) = result {
+                break;
+            }
+        }
+        if let Ok(mut clients) = clients_mtx.lock() {
+            clients.remove(&id);
+        }
+        stream.shutdown_stream();
+    });
+}
+
+/// Reads lines from a client until it disconnects, then forgets about it.
+fn spawn_client_reader<S: SocketStream>(
+    id: usize, stream: S, requests_mtx: RequestsMutex,
+    clients_mtx: ClientsMutex
+) -> () {
+    thread::spawn(move || {
+        let mut reader: BufReader<S> = BufReader::new(stream);
+        let mut buffer: String = String::new();
+        loop {
+            match reader.read_line(&mut buffer) {
+                Err(_) | Ok(0) => break,
+                Ok(_) => if buffer.trim().len() != 0 {
+                    let envelope: CommandEnvelope
+                        = CommandEnvelope::parse(buffer.trim());
+                    if let Ok(mut requests) = requests_mtx.lock() {
+                        requests.push((id, envelope));
+                    } else {
+                        break;
+                    }
+                }
+            }
+            buffer.clear();
+        }
+        if let Ok(mut clients) = clients_mtx.lock() {
+            clients.remove(&id);
+        }
+    });
+}
+
+/// A transport that listens on a socket and accepts any number of clients.
+///
+/// Every client sends newline-delimited commands, just like through stdin, and
+/// gets its own replies back on the same connection.
+///
+/// Replies and events are handed to each client's own writer thread, so a
+/// client that stops reading only ever holds itself up.
+pub struct SocketInterface<S: SocketStream> {
+    requests_mtx: RequestsMutex,
+    clients_mtx: ClientsMutex,
+    stream: PhantomData<S>
+}
+
+impl<S: SocketStream> SocketInterface<S> {
+    /// Polls the given (non-blocking) accept function every tick, handing
+    /// every stream it accepts off to its own reader and writer threads.
+    fn new<F>(
+        mut accept: F, ticks: Receiver<Instant>, done: Arc<AtomicBool>
+    ) -> SocketInterface<S>
+    where F: FnMut() -> io::Result<S> + Send + 'static {
+        let main_requests_mtx: RequestsMutex = Arc::new(Mutex::new(vec!()));
+        let accept_requests_mtx: RequestsMutex
+            = Arc::clone(&main_requests_mtx);
+        let main_clients_mtx: ClientsMutex
+            = Arc::new(Mutex::new(HashMap::new()));
+        let accept_clients_mtx: ClientsMutex = Arc::clone(&main_clients_mtx);
+
+        thread::spawn(move || {
+            let mut next_id: usize = 0;
+            while !done.load(Ordering::Relaxed) {
+                select! {
+                    recv(ticks) -> _ => {
+                        while let Ok(stream) = accept() {
+                            let maybe_writer: io::Result<S> = stream
+                                .set_blocking()
+                                .and_then(|_| stream
+                                    .set_write_timeout_to(WRITE_TIMEOUT))
+                                .and_then(|_| stream.try_clone_stream());
+                            let writer: S = match maybe_writer {
+                                Err(_) => continue,
+                                Ok(writer) => writer
+                            };
+                            let (outgoing_tx, outgoing_rx): (
+                                Sender<String>, Receiver<String>
+                            ) = unbounded();
+                            if let Ok(mut clients) = accept_clients_mtx.lock() {
+                                clients.insert(next_id, outgoing_tx);
+                            }
+                            spawn_client_writer(
+                                next_id,
+                                writer,
+                                outgoing_rx,
+                                Arc::clone(&accept_clients_mtx));
+                            spawn_client_reader(
+                                next_id,
+                                stream,
+                                Arc::clone(&accept_requests_mtx),
+                                Arc::clone(&accept_clients_mtx));
+                            next_id += 1;
+                        }
+                    }
+                }
+            }
+        });
+
+        return SocketInterface {
+            requests_mtx: main_requests_mtx,
+            clients_mtx: main_clients_mtx,
+            stream: PhantomData
+        }
+    }
+}
+
+impl SocketInterface<TcpStream> {
+    pub fn bind_tcp(
+        address: &str, ticks: Receiver<Instant>, done: Arc<AtomicBool>
+    ) -> Result<SocketInterface<TcpStream>, String> {
+        let listener: TcpListener = TcpListener::bind(address)
+            .and_then(|listener| listener.set_nonblocking(true)
+                .map(|_| listener))
+            .map_err(|e| format!(
+                "Failed to listen for TCP clients on {}: {}", address, e))?;
+        return Ok(SocketInterface::new(
+            move || listener.accept().map(|(stream, _)| stream),
+            ticks,
+            done));
+    }
+}
+
+#[cfg(unix)]
+impl SocketInterface<UnixStream> {
+    pub fn bind_unix(
+        path: &Path, ticks: Receiver<Instant>, done: Arc<AtomicBool>
+    ) -> Result<SocketInterface<UnixStream>, String> {
+        // A socket left behind by a previous run would stop us from binding,
+        // but we don't want to clobber anything that isn't a socket.
+        if let Ok(metadata) = fs::metadata(path) {
+            if metadata.file_type().is_socket() {
+                fs::remove_file(path).map_err(|e| format!(
+                    "Failed to remove old Unix socket {}: {}",
+                    path.display(), e))?;
+            }
+        }
+        let listener: UnixListener = UnixListener::bind(path)
+            .and_then(|listener| listener.set_nonblocking(true)
+                .map(|_| listener))
+            .map_err(|e| format!(
+                "Failed to listen for clients on {}: {}", path.display(), e))?;
+        return Ok(SocketInterface::new(
+            move || listener.accept().map(|(stream, _)| stream),
+            ticks,
+            done));
+    }
+}
+
+impl<S: SocketStream> CommandTransport for SocketInterface<S> {
+    fn read_into(
+        &mut self, requests: &mut Vec<(usize, CommandEnvelope)>
+    ) -> () {
+        if let Ok(mut received) = self.requests_mtx.lock() {
+            requests.extend(received.drain(..));
+        }
+    }
+
+    fn write(&mut self, client: usize, s: String) -> Result<(), String> {
+        if let Ok(clients) = self.clients_mtx.lock() {
+            // The client's own thread does the actual writing.
+            return match clients.get(&client) {
+                None => Err(String::from("Client has disconnected.")),
+                Some(outgoing) => outgoing.send(s)
+                    .map_err(|_| String::from("Client has disconnected."))
+            };
+        } else {
+            return Err(String::from("Failed to lock clients."));
+        }
+    }
+
+    fn get_clients(&self) -> Vec<usize> {
+        if let Ok(clients) = self.clients_mtx.lock() {
+            return clients.keys().cloned().collect();
+        } else {
+            return vec!();
+        }
+    }
+}
@@ -9,9 +9,11 @@ use std::{
     }
 };
 
-use crate::controller::application_command::ApplicationCommand;
+use crate::controller::{
+    command_envelope::CommandEnvelope,
+    command_transport::CommandTransport
+};
 use crossbeam_channel::{select, Receiver};
-use serde_json;
 
 struct StdinReader {
     reader: BufReader<io::Stdin>,
@@ -30,20 +32,17 @@ impl StdinReader {
     }
 
     pub fn read_into(
-        &mut self, commands_mtx: Arc<Mutex<Vec<ApplicationCommand>>>
+        &mut self, commands_mtx: Arc<Mutex<Vec<CommandEnvelope>>>
     ) -> Result<(), String> {
         let mut buffer: String = String::new();
         while self.has_data() {
             if let Err(_) = self.reader.read_line(&mut buffer) {
                 return Err(String::from("Failed to read from stdin buffer."));
             }
-            let maybe_command: serde_json::Result<ApplicationCommand>
-                = serde_json::from_str(buffer.trim());
+            let envelope: CommandEnvelope
+                = CommandEnvelope::parse(buffer.trim());
             if let Ok(mut commands) = commands_mtx.lock() {
-                match maybe_command {
-                    Err(_) => commands.push(ApplicationCommand::Unsupported),
-                    Ok(command) => commands.push(command)
-                }
+                commands.push(envelope);
             } else {
                 return Err(String::from("Failed to lock commands."));
             }
@@ -79,20 +78,25 @@ impl StdoutWriter {
     }
 }
 
+/// The only client of stdio is whoever spawned us, so it always has this ID.
+const STDIO_CLIENT: usize = 0;
+
 pub struct StdioInterface {
     read_thread: thread::JoinHandle<()>,
+    commands_mtx: Arc<Mutex<Vec<CommandEnvelope>>>,
     writer: StdoutWriter
 }
 
 impl StdioInterface {
     pub fn new(
-        ticks: Receiver<Instant>, done: Arc<AtomicBool>,
-        commands_mtx: Arc<Mutex<Vec<ApplicationCommand>>>
+        ticks: Receiver<Instant>, done: Arc<AtomicBool>
     ) -> StdioInterface {
+        let commands_mtx: Arc<Mutex<Vec<CommandEnvelope>>>
+            = Arc::new(Mutex::new(vec!()));
         let mut reader: StdinReader = StdinReader::new();
         let read_done: Arc<AtomicBool> = Arc::clone(&done);
         let read_ticks: Receiver<Instant> = ticks.clone(); 
-        let read_mtx: Arc<Mutex<Vec<ApplicationCommand>>>
+        let read_mtx: Arc<Mutex<Vec<CommandEnvelope>>>
             = Arc::clone(&commands_mtx);
 
         let read_thread: thread::JoinHandle<()> = thread::spawn(move || {
@@ -110,15 +114,32 @@ impl StdioInterface {
 
         return StdioInterface {
             read_thread: read_thread,
+            commands_mtx: commands_mtx,
             writer: StdoutWriter::new()
         }
     }
 
-    pub fn write(&mut self, s: String) -> Result<(), String> {
+    pub fn join(self) -> () {
+        self.read_thread.join().expect("Failed to join read thread.");
+    }
+}
+
+impl CommandTransport for StdioInterface {
+    fn read_into(
+        &mut self, requests: &mut Vec<(usize, CommandEnvelope)>
+    ) -> () {
+        if let Ok(mut commands) = self.commands_mtx.lock() {
+            for envelope in commands.drain(..) {
+                requests.push((STDIO_CLIENT, envelope));
+            }
+        }
+    }
+
+    fn write(&mut self, _client: usize, s: String) -> Result<(), String> {
         return self.writer.write(s);
     }
 
-    pub fn join(self) -> () {
-        self.read_thread.join().expect("Failed to join read thread.");
+    fn get_clients(&self) -> Vec<usize> {
+        return vec!(STDIO_CLIENT);
     }
 }
@@ -0,0 +1,169 @@
+use std::{
+    collections::HashMap,
+    io,
+    net::{TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering}
+    }
+};
+
+use crate::controller::{
+    command_envelope::CommandEnvelope,
+    command_transport::{CommandTransport, RequestsMutex}
+};
+use crossbeam_channel::{select, unbounded, Receiver, Sender};
+use tungstenite::{Error, Message, WebSocket};
+
+/// How long a client's thread waits on a read before checking if it has any
+/// replies or events to send. This is about as long as a tick.
+const POLL_INTERVAL: Duration = Duration::from_millis(16);
+
+type ClientsMutex = Arc<Mutex<HashMap<usize, Sender<String>>>>;
+
+/// Performs the handshake with a client, then shuttles messages to and from it
+/// until it disconnects.
+fn spawn_client(
+    id: usize, stream: TcpStream, outgoing: Receiver<String>,
+    requests_mtx: RequestsMutex, clients_mtx: ClientsMutex
+) -> () {
+    thread::spawn(move || {
+        if let Ok(mut socket) = tungstenite::accept(stream) {
+            let timeout: io::Result<()> = socket.get_mut()
+                .set_read_timeout(Some(POLL_INTERVAL));
+            if let Ok(_) = timeout {
+                serve_client(id, &mut socket, outgoing, requests_mtx);
+            }
+        }
+        if let Ok(mut clients) = clients_mtx.lock() {
+            clients.remove(&id);
+        }
+    });
+}
+
+fn serve_client(
+    id: usize, socket: &mut WebSocket<TcpStream>, outgoing: Receiver<String>,
+    requests_mtx: RequestsMutex
+) -> () {
+    loop {
+        match socket.read_message() {
+            Ok(Message::Text(text)) => {
+                let envelope: CommandEnvelope
+                    = CommandEnvelope::parse(text.trim());
+                if let Ok(mut requests) = requests_mtx.lock() {
+                    requests.push((id, envelope));
+                } else {
+                    return;
+                }
+            },
+            Ok(Message::Close(_)) => return,
+            // Pings get answered on their own, and we don't take anything
+            // other than text.
+            Ok(_) => (),
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::WouldBlock
+                || e.kind() == io::ErrorKind::TimedOut => (),
+            Err(_) => return
+        }
+        for s in outgoing.try_iter() {
+            if let Err(_) = socket.write_message(Message::Text(s)) {
+                return;
+            }
+        }
+    }
+}
+
+/// A transport that speaks the same JSON as every other transport, just over
+/// WebSocket text messages instead of lines.
+///
+/// Every message sent by a client should contain exactly one command, and every
+/// reply and event is sent back as its own message.
+pub struct WebSocketInterface {
+    requests_mtx: RequestsMutex,
+    clients_mtx: ClientsMutex
+}
+
+impl WebSocketInterface {
+    pub fn new(
+        address: &str, ticks: Receiver<Instant>, done: Arc<AtomicBool>
+    ) -> Result<WebSocketInterface, String> {
+        let listener: TcpListener = TcpListener::bind(address)
+            .and_then(|listener| listener.set_nonblocking(true)
+                .map(|_| listener))
+            .map_err(|e| format!(
+                "Failed to listen for WebSocket clients on {}: {}",
+                address, e))?;
+
+        let main_requests_mtx: RequestsMutex = Arc::new(Mutex::new(vec!()));
+        let accept_requests_mtx: RequestsMutex
+            = Arc::clone(&main_requests_mtx);
+        let main_clients_mtx: ClientsMutex
+            = Arc::new(Mutex::new(HashMap::new()));
+        let accept_clients_mtx: ClientsMutex = Arc::clone(&main_clients_mtx);
+
+        thread::spawn(move || {
+            let mut next_id: usize = 0;
+            while !done.load(Ordering::Relaxed) {
+                select! {
+                    recv(ticks) -> _ => {
+                        while let Ok((stream, _)) = listener.accept() {
+                            if let Err(_) = stream.set_nonblocking(false) {
+                                continue;
+                            }
+                            let (outgoing_tx, outgoing_rx): (
+                                Sender<String>, Receiver<String>
+                            ) = unbounded();
+                            if let Ok(mut clients) = accept_clients_mtx.lock() {
+                                clients.insert(next_id, outgoing_tx);
+                            }
+                            spawn_client(
+                                next_id,
+                                stream,
+                                outgoing_rx,
+                                Arc::clone(&accept_requests_mtx),
+                                Arc::clone(&accept_clients_mtx));
+                            next_id += 1;
+                        }
+                    }
+                }
+            }
+        });
+
+        return Ok(WebSocketInterface {
+            requests_mtx: main_requests_mtx,
+            clients_mtx: main_clients_mtx
+        });
+    }
+}
+
+impl CommandTransport for WebSocketInterface {
+    fn read_into(
+        &mut self, requests: &mut Vec<(usize, CommandEnvelope)>
+    ) -> () {
+        if let Ok(mut received) = self.requests_mtx.lock() {
+            requests.extend(received.drain(..));
+        }
+    }
+
+    fn write(&mut self, client: usize, s: String) -> Result<(), String> {
+        if let Ok(clients) = self.clients_mtx.lock() {
+            // The client's own thread does the actual sending.
+            return match clients.get(&client) {
+                None => Err(String::from("Client has disconnected.")),
+                Some(outgoing) => outgoing.send(s)
+                    .map_err(|_| String::from("Client has disconnected."))
+            };
+        } else {
+            return Err(String::from("Failed to lock clients."));
+        }
+    }
+
+    fn get_clients(&self) -> Vec<usize> {
+        if let Ok(clients) = self.clients_mtx.lock() {
+            return clients.keys().cloned().collect();
+        } else {
+            return vec!();
+        }
+    }
+}
@@ -5,5 +5,16 @@ mod controller;
 extern crate structure;
 
 pub use model::input::script_event::ScriptEvent;
+pub use model::application_error::{ApplicationError, ErrorCode};
+pub use model::application_event::ApplicationEvent;
+pub use model::keyboard_mouse::{KeyboardMouseConfig, StickSide};
+pub use model::remote_client::RemoteClientConfig;
+pub use model::remote_interface::{RemoteConfig, RemoteMessage, RemoteReply};
 pub use controller::application_command::ApplicationCommand;
+pub use controller::application_response::ApplicationResponse;
 pub use controller::application_controller::ApplicationController;
+pub use controller::client_controller::ClientController;
+pub use controller::application_options::{
+    ApplicationOptions, DEFAULT_CONFIG_PATH
+};
+pub use controller::command_transport::TransportConfig;
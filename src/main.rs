@@ -1,6 +1,228 @@
-use vhid_companion::ApplicationController;
+use std::{
+    path::{Path, PathBuf},
+    process
+};
 
-fn main() {
-    let controller: ApplicationController = ApplicationController::new();
+use clap::{Parser, Subcommand};
+use vhid_companion::{
+    ApplicationController, ApplicationOptions, ClientController,
+    KeyboardMouseConfig, RemoteClientConfig, DEFAULT_CONFIG_PATH
+};
+
+#[derive(Parser)]
+#[clap(
+    version,
+    about = "Forwards controller input to the vhid sysmodule.",
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[clap(subcommand)]
+    mode: Option<Mode>,
+
+    /// A TOML file to load options and profiles from. Flags override anything
+    /// in it. Defaults to ./vhid_companion.toml, if it exists.
+    #[clap(long)]
+    config: Option<PathBuf>,
+    /// A profile from the config file to load on startup.
+    #[clap(long)]
+    profile: Option<String>,
+    /// Don't read input from the SDL event server.
+    #[clap(long)]
+    no_sdl_server: bool,
+    /// Where to find the SDL event server.
+    #[clap(long)]
+    server_path: Option<PathBuf>,
+    /// Read gamepads straight from /dev/input. Linux only.
+    #[clap(long)]
+    evdev: bool,
+    /// Use the keyboard and mouse as another gamepad, with the mapping from
+    /// the config file if there is one. Linux only.
+    #[clap(long)]
+    keyboard_mouse: bool,
+    /// The address to bind the sysmodule's UDP socket to.
+    #[clap(long)]
+    bind: Option<String>,
+    /// The port that the sysmodule listens on.
+    #[clap(long)]
+    port: Option<u16>,
+    /// How many times per second to update.
+    #[clap(long)]
+    rate: Option<u32>,
+    /// An IP to send packets to. Can be given more than once.
+    #[clap(long = "ip")]
+    ips: Vec<String>,
+    /// Start sending packets right away.
+    #[clap(long)]
+    connect: bool,
+    /// Don't read commands from stdin or write anything to stdout.
+    #[clap(long)]
+    headless: bool,
+    /// Accept input from remote players over TCP at this address.
+    #[clap(long)]
+    remote_tcp: Option<String>,
+    /// Accept input from remote players over UDP at this address.
+    #[clap(long)]
+    remote_udp: Option<String>,
+    /// The secret that remote players need to join.
+    #[clap(long)]
+    remote_secret: Option<String>,
+    /// Also accept commands on this localhost TCP port.
+    #[clap(long)]
+    tcp: Option<u16>,
+    /// Also accept commands on a Unix socket at this path.
+    #[clap(long)]
+    unix: Option<PathBuf>,
+    /// Also accept commands on this localhost WebSocket port.
+    #[clap(long)]
+    websocket: Option<u16>
+}
+
+#[derive(Subcommand)]
+enum Mode {
+    /// Plays on another instance as a remote player, sending it every local
+    /// controller instead of sending them to a sysmodule.
+    Client {
+        /// The address that the other instance accepts remote players on.
+        address: String,
+        /// Use UDP instead of TCP.
+        #[clap(long)]
+        udp: bool,
+        /// The secret that the other instance wants.
+        #[clap(long)]
+        secret: Option<String>,
+        /// A TOML file to load options from, like which inputs to read.
+        #[clap(long)]
+        config: Option<PathBuf>,
+        /// Where to find the SDL event server.
+        #[clap(long)]
+        server_path: Option<PathBuf>,
+        /// How many times per second to update.
+        #[clap(long)]
+        rate: Option<u32>
+    }
+}
+
+/// Loads the given config file, or the default one if it exists.
+///
+/// A config file that was given has to exist, but the default one doesn't,
+/// since profiles can be saved to it later.
+fn load_config(config: &Option<PathBuf>) -> Result<ApplicationOptions, String> {
+    let default_path: &Path = Path::new(DEFAULT_CONFIG_PATH);
+    return match config {
+        Some(path) => ApplicationOptions::load(path),
+        None if default_path.exists() => ApplicationOptions::load(default_path),
+        None => Ok(ApplicationOptions::default())
+    };
+}
+
+impl Cli {
+    /// Loads the config file, then applies every flag on top of it.
+    fn into_options(self) -> Result<ApplicationOptions, String> {
+        let mut options: ApplicationOptions = load_config(&self.config)?;
+        if let Some(profile) = self.profile {
+            options.profile = Some(profile);
+        }
+        if self.no_sdl_server {
+            options.sdl_server = false;
+        }
+        if let Some(server_path) = self.server_path {
+            options.server_path = server_path;
+        }
+        options.evdev |= self.evdev;
+        if self.keyboard_mouse && options.keyboard_mouse.is_none() {
+            options.keyboard_mouse = Some(KeyboardMouseConfig::default());
+        }
+        if let Some(bind) = self.bind {
+            options.bind = bind;
+        }
+        if let Some(port) = self.port {
+            options.port = port;
+        }
+        if let Some(rate) = self.rate {
+            options.rate = rate;
+        }
+        if self.ips.len() != 0 {
+            options.ips = self.ips;
+        }
+        options.connect |= self.connect;
+        options.headless |= self.headless;
+        if let Some(address) = self.remote_tcp {
+            options.remote.tcp = Some(address);
+        }
+        if let Some(address) = self.remote_udp {
+            options.remote.udp = Some(address);
+        }
+        if let Some(secret) = self.remote_secret {
+            options.remote.secret = Some(secret);
+        }
+        if let Some(tcp) = self.tcp {
+            options.tcp = Some(tcp);
+        }
+        if let Some(unix) = self.unix {
+            options.unix = Some(unix);
+        }
+        if let Some(websocket) = self.websocket {
+            options.websocket = Some(websocket);
+        }
+
+        options.validate()?;
+        return Ok(options);
+    }
+}
+
+fn run_client(
+    config: &Option<PathBuf>, server_path: Option<PathBuf>, rate: Option<u32>,
+    client_config: RemoteClientConfig
+) -> Result<(), String> {
+    let mut options: ApplicationOptions = load_config(config)?;
+    if let Some(server_path) = server_path {
+        options.server_path = server_path;
+    }
+    if let Some(rate) = rate {
+        options.rate = rate;
+    }
+    options.validate()?;
+    let controller: ClientController
+        = ClientController::new(options, client_config)?;
     controller.join();
+    return Ok(());
+}
+
+fn main() {
+    let mut cli: Cli = Cli::parse();
+    match cli.mode.take() {
+        Some(Mode::Client {
+            address, udp, secret, config, server_path, rate
+        }) => {
+            let client_config: RemoteClientConfig = RemoteClientConfig {
+                address: address,
+                udp: udp,
+                secret: secret
+            };
+            if let Err(e) = run_client(
+                &config, server_path, rate, client_config
+            ) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        None => {
+            let options: ApplicationOptions = match cli.into_options() {
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                },
+                Ok(options) => options
+            };
+            let controller: ApplicationController
+                = match ApplicationController::new(options) {
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        process::exit(1);
+                    },
+                    Ok(controller) => controller
+                };
+            controller.join();
+        }
+    }
 }
@@ -0,0 +1,47 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+/// Machine-readable codes for everything that can go wrong while handling a
+/// command.
+///
+/// Frontends should match on these rather than on the messages, since the
+/// messages are only meant for humans and are free to change.
+pub enum ErrorCode {
+    ParseError,
+    UnsupportedCommand,
+    InvalidArgument,
+    LockFailed,
+    InvalidGamepad,
+    NoIps,
+    NotConnected,
+    AlreadyConnected,
+    AlreadyDisconnected,
+    ServerError,
+    ConfigError,
+    UnknownProfile,
+    UnknownScript,
+    ScriptNotRunning
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ApplicationError {
+    code: ErrorCode,
+    message: String
+}
+
+impl ApplicationError {
+    pub fn new(code: ErrorCode, message: &str) -> ApplicationError {
+        return ApplicationError {
+            code: code,
+            message: String::from(message)
+        }
+    }
+
+    pub fn get_code(&self) -> ErrorCode {
+        return self.code;
+    }
+
+    pub fn get_message(&self) -> String {
+        return self.message.clone();
+    }
+}
@@ -0,0 +1,20 @@
+use crate::model::input::snapshot::GamepadSnapshot;
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Serialize, Deserialize)]
+/// Events that happen on their own, rather than in response to a command.
+///
+/// These are pushed out to the frontend as they happen, so it doesn't have to
+/// poll for them.
+pub enum ApplicationEvent {
+    ControllerAdded { i: usize },
+    ControllerRemoved { i: usize },
+    ControllersSwapped { i: usize, j: usize },
+    ConnectionLost { message: String },
+    // Scripts go by the handle they were started with.
+    ScriptFinished { i: usize, handle: usize },
+    ScriptCancelled { i: usize, handle: usize },
+    ServerExited,
+    // Only sent to clients that subscribed to them.
+    GamepadStates { gamepads: Vec<GamepadSnapshot> }
+}
@@ -1,7 +1,7 @@
 use std::{
-    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    path::Path,
     thread::self,
-    time::Instant,
+    time::{Duration, Instant},
     sync::{
         Arc, Mutex,
         atomic::{AtomicBool, Ordering}
@@ -9,14 +9,28 @@ use std::{
 };
 
 use crate::model::{
+    application_error::{ApplicationError, ErrorCode},
+    application_event::ApplicationEvent,
     input::{
-        manager::GamepadManager,
-        script_event::ScriptEvent
+        axis_map::AxisMap,
+        button_map::{ButtonMap, PhysicalButton},
+        device_info::DeviceInfo,
+        gamepad::{GamepadButton, GamepadSettings, GamepadType, MergePolicy},
+        input_filter::InputFilter,
+        macro_binding::MacroBinding,
+        manager::{GamepadManager, NUM_GAMEPADS},
+        script_event::ScriptEvent,
+        script_status::ScriptStatus,
+        script_text,
+        snapshot::GamepadSnapshot,
+        turbo::{TurboButton, TurboMap, TurboMode}
     },
-    sdl_interface::{SdlReader, SdlWriter},
+    input_source::{InputBuffer, InputSource},
+    profile::{Profile, ProfileStore},
+    script_store::ScriptStore,
     sysmodule_interface::SysmoduleInterface
 };
-use crossbeam_channel::{select, Receiver};
+use crossbeam_channel::{select, unbounded, Receiver, Sender};
 use sdl_event_server::SdlEvent;
 
 struct ApplicationState {
@@ -49,10 +63,33 @@ impl ApplicationState {
     }
 }
 
+fn manager_lock_error() -> ApplicationError {
+    return ApplicationError::new(
+        ErrorCode::LockFailed, "Failed to lock gamepad manager.");
+}
+
+fn sys_if_lock_error() -> ApplicationError {
+    return ApplicationError::new(
+        ErrorCode::LockFailed, "Failed to lock sysmodule interface.");
+}
+
+fn check_index(i: usize) -> Result<(), ApplicationError> {
+    if i < NUM_GAMEPADS {
+        return Ok(());
+    } else {
+        return Err(ApplicationError::new(
+            ErrorCode::InvalidGamepad,
+            &format!("Gamepad index must be less than {}.", NUM_GAMEPADS)));
+    }
+}
+
 pub struct ApplicationModel {
     gamepad_manager_mtx: Arc<Mutex<GamepadManager>>,
     sys_if_mtx: Arc<Mutex<SysmoduleInterface>>,
-    sdl_writer: SdlWriter,
+    sources: Vec<Box<dyn InputSource>>,
+    events_rx: Receiver<ApplicationEvent>,
+    profiles: ProfileStore,
+    scripts: ScriptStore,
 
     state: ApplicationState,
     update_thread: thread::JoinHandle<()>
@@ -60,9 +97,10 @@ pub struct ApplicationModel {
 
 impl ApplicationModel {
     pub fn new(
-        ticks: Receiver<Instant>, done: Arc<AtomicBool>
-    ) -> ApplicationModel {
-        let mut events: Vec<SdlEvent> = vec!();
+        ticks: Receiver<Instant>, done: Arc<AtomicBool>,
+        mut sources: Vec<Box<dyn InputSource>>, bind: &str, port: u16,
+        config_path: &Path, frame: Duration
+    ) -> Result<ApplicationModel, String> {
         let state_done: Arc<AtomicBool> = Arc::clone(&done);
         let state: ApplicationState = ApplicationState::new(state_done);
 
@@ -71,214 +109,676 @@ impl ApplicationModel {
         let update_done: Arc<AtomicBool> = Arc::clone(&done);
 
         let main_manager_mtx: Arc<Mutex<GamepadManager>>
-            = Arc::new(Mutex::new(GamepadManager::new()));
+            = Arc::new(Mutex::new(GamepadManager::new(
+                frame, ScriptStore::new(config_path))));
         let update_manager_mtx: Arc<Mutex<GamepadManager>>
             = Arc::clone(&main_manager_mtx);
 
         let main_sys_if_mtx: Arc<Mutex<SysmoduleInterface>>
-            = Arc::new(Mutex::new(SysmoduleInterface::new()));
+            = Arc::new(Mutex::new(SysmoduleInterface::new(bind, port)?));
         let update_sys_if_mtx: Arc<Mutex<SysmoduleInterface>>
             = Arc::clone(&main_sys_if_mtx);
 
-        let mut server: Child = Command::new("./sdl_event_server")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()
-            .expect("Failed to execute SDL server.");
-        let server_stdout: ChildStdout = server.stdout
-            .take()
-            .expect("Failed to pipe server's stdout.");
-        let server_stdin: ChildStdin = server.stdin
-            .take()
-            .expect("Failed to pipe server's stdin.");
-
-        let mut update_reader: SdlReader = SdlReader::new(server_stdout);
-        let main_writer: SdlWriter = SdlWriter::new(server, server_stdin);
+        let (events_tx, events_rx): (
+            Sender<ApplicationEvent>, Receiver<ApplicationEvent>
+        ) = unbounded();
+        let input: InputBuffer = InputBuffer::new(events_tx.clone());
+        for k in 0..sources.len() {
+            if let Err(e) = sources[k].start(input.for_source(k)) {
+                // The ones that did start are already reading, so they have to
+                // be stopped again.
+                done.store(true, Ordering::SeqCst);
+                for source in sources[..k].iter_mut() {
+                    source.stop().unwrap_or(());
+                }
+                return Err(format!("Failed to start input source: {}", e));
+            }
+        }
 
         let update_thread: thread::JoinHandle<()> = thread::spawn(move || {
             while !update_done.load(Ordering::Relaxed) {
                 select! {
                     recv(update_ticks) -> _ => {
-                        if let Err(_) = update_reader.read_into(&mut events) {
-                            update_done.store(true, Ordering::SeqCst);
-                        }
+                        let (mut events, devices): (
+                            Vec<SdlEvent>, Vec<(u32, DeviceInfo)>
+                        ) = input.take();
                         if let Ok(mut manager) = update_manager_mtx.lock() {
+                            for (which, device) in devices {
+                                manager.identify(which, device);
+                            }
                             manager.update(&mut events);
                             if update_connected.load(Ordering::Relaxed) {
                                 if let Ok(sys_if) = update_sys_if_mtx.lock() {
-                                    if let Err(_) = sys_if.udp_update(
+                                    if let Err(e) = sys_if.udp_update(
                                         manager.get_anarchy_mode(),
                                         manager.get_gamepads()
                                     ) {
-                                        update_done.store(true, Ordering::SeqCst);
+                                        update_connected
+                                            .store(false, Ordering::SeqCst);
+                                        events_tx.send(
+                                            ApplicationEvent::ConnectionLost {
+                                                message: e
+                                            }
+                                        ).unwrap_or(());
                                     }
                                 }
                             }
+                            for event in manager.take_events() {
+                                events_tx.send(event).unwrap_or(());
+                            }
                         }
                     }
                 }
             }
         });
 
-        return ApplicationModel {
+        return Ok(ApplicationModel {
             gamepad_manager_mtx: main_manager_mtx,
             sys_if_mtx: main_sys_if_mtx,
-            sdl_writer: main_writer,
+            sources: sources,
+            events_rx: events_rx,
+            profiles: ProfileStore::new(config_path),
+            scripts: ScriptStore::new(config_path),
 
             state: state,
             update_thread: update_thread
-        }
+        });
     }
 
-    pub fn get_anarchy_mode(&self) -> Result<bool, String> {
+    pub fn get_anarchy_mode(&self) -> Result<bool, ApplicationError> {
         if let Ok(gamepad_manager) = self.gamepad_manager_mtx.lock() {
             return Ok(gamepad_manager.get_anarchy_mode());
         } else {
-            return Err(String::from("Failed to lock gamepad manager."));
+            return Err(manager_lock_error());
         }
     }
 
     pub fn set_anarchy_mode(
         &mut self, anarchy_mode: bool
-    ) -> Result<(), String> {
+    ) -> Result<(), ApplicationError> {
         if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
             gamepad_manager.set_anarchy_mode(anarchy_mode);
             return Ok(());
         } else {
-            return Err(String::from("Failed to lock gamepad manager."));
+            return Err(manager_lock_error());
         }
     }
 
+    /// Returns the events that happened since the last time this was called.
+    pub fn take_events(&self) -> Vec<ApplicationEvent> {
+        return self.events_rx.try_iter().collect();
+    }
+
     pub fn get_done(&self) -> Arc<AtomicBool> {
         return self.state.get_done();
     }
 
-    pub fn get_ips(&self) -> Result<Vec<String>, String> {
+    pub fn get_ips(&self) -> Result<Vec<String>, ApplicationError> {
         if let Ok(sys_if) = self.sys_if_mtx.lock() {
             return Ok(sys_if.get_ips())
         } else {
-            return Err(String::from("Failed to lock sysmodule interface."));
+            return Err(sys_if_lock_error());
         }
     }
 
-    pub fn set_ips(&mut self, ips: Vec<String>) -> Result<(), String> {
+    pub fn set_ips(
+        &mut self, ips: Vec<String>
+    ) -> Result<(), ApplicationError> {
         if let Ok(mut sys_if) = self.sys_if_mtx.lock() {
             sys_if.set_ips(ips);
             return Ok(());
         } else {
-            return Err(String::from("Failed to lock sysmodule interface."));
+            return Err(sys_if_lock_error());
+        }
+    }
+
+    pub fn get_snapshots(
+        &self
+    ) -> Result<Vec<GamepadSnapshot>, ApplicationError> {
+        if let Ok(gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            return Ok(gamepad_manager.get_snapshots());
+        } else {
+            return Err(manager_lock_error());
         }
     }
 
-    pub fn get_delay(&self, i: usize) -> Result<usize, String> {
+    pub fn get_delay(&self, i: usize) -> Result<usize, ApplicationError> {
+        check_index(i)?;
         if let Ok(gamepad_manager) = self.gamepad_manager_mtx.lock() {
             return Ok(gamepad_manager.get_delay(i));
         } else {
-            return Err(String::from("Failed to lock gamepad manager."));
+            return Err(manager_lock_error());
         }
     }
 
-    pub fn set_delay(&mut self, i: usize, delay: usize) -> Result<(), String> {
+    pub fn set_delay(
+        &mut self, i: usize, delay: usize
+    ) -> Result<(), ApplicationError> {
+        check_index(i)?;
         if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
             gamepad_manager.set_delay(i, delay);
             return Ok(());
         } else {
-            return Err(String::from("Failed to lock gamepad manager."));
+            return Err(manager_lock_error());
         }
     }
 
-    pub fn get_left_deadzone(&self, i: usize) -> Result<f32, String> {
+    pub fn get_button_map(
+        &self, i: usize
+    ) -> Result<ButtonMap, ApplicationError> {
+        check_index(i)?;
+        if let Ok(gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            return Ok(gamepad_manager.get_button_map(i));
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    /// Maps a physical button to any number of buttons, leaving the rest of
+    /// the gamepad's mappings alone.
+    pub fn set_button_mapping(
+        &mut self, i: usize, from: PhysicalButton, to: Vec<GamepadButton>
+    ) -> Result<(), ApplicationError> {
+        check_index(i)?;
+        if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            let mut button_map: ButtonMap = gamepad_manager.get_button_map(i);
+            button_map.set(from, &to);
+            gamepad_manager.set_button_map(i, button_map);
+            return Ok(());
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    pub fn reset_button_map(
+        &mut self, i: usize
+    ) -> Result<(), ApplicationError> {
+        check_index(i)?;
+        if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            let gamepad_type: GamepadType
+                = *gamepad_manager.get_gamepads()[i].get_gamepad_type();
+            gamepad_manager
+                .set_button_map(i, ButtonMap::for_type(gamepad_type));
+            return Ok(());
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    pub fn set_gamepad_type(
+        &mut self, i: usize, gamepad_type: GamepadType
+    ) -> Result<(), ApplicationError> {
+        check_index(i)?;
+        if let GamepadType::Disconnected = gamepad_type {
+            return Err(ApplicationError::new(
+                ErrorCode::InvalidArgument,
+                "Disconnect the controller instead."));
+        }
+        if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            if gamepad_manager.set_gamepad_type(i, gamepad_type) {
+                return Ok(());
+            } else {
+                return Err(ApplicationError::new(
+                    ErrorCode::InvalidGamepad,
+                    "There's no controller in that slot."));
+            }
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    pub fn get_axis_map(&self, i: usize) -> Result<AxisMap, ApplicationError> {
+        check_index(i)?;
+        if let Ok(gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            return Ok(gamepad_manager.get_axis_map(i));
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    pub fn set_axis_map(
+        &mut self, i: usize, axis_map: AxisMap
+    ) -> Result<(), ApplicationError> {
+        check_index(i)?;
+        if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            gamepad_manager.set_axis_map(i, axis_map);
+            return Ok(());
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    pub fn list_scripts(&self) -> Result<Vec<String>, ApplicationError> {
+        return self.scripts.list();
+    }
+
+    pub fn get_script(
+        &self, name: &str
+    ) -> Result<Vec<ScriptEvent>, ApplicationError> {
+        return self.scripts.load(name);
+    }
+
+    pub fn save_script(
+        &self, name: &str, script: &Vec<ScriptEvent>
+    ) -> Result<(), ApplicationError> {
+        return self.scripts.save(name, script);
+    }
+
+    pub fn delete_script(&self, name: &str) -> Result<(), ApplicationError> {
+        return self.scripts.delete(name);
+    }
+
+    /// Parses a script written as text, keeping where it went wrong in the
+    /// message.
+    pub fn parse_script_text(
+        &self, text: &str
+    ) -> Result<Vec<ScriptEvent>, ApplicationError> {
+        return script_text::parse(text).map_err(|e| ApplicationError::new(
+            ErrorCode::ParseError, &e.get_message()));
+    }
+
+    pub fn format_script_text(
+        &self, script: &[ScriptEvent]
+    ) -> Result<String, ApplicationError> {
+        return script_text::print(script).map_err(|e| ApplicationError::new(
+            ErrorCode::InvalidArgument, &e));
+    }
+
+    pub fn run_script_text(
+        &mut self, i: usize, text: &str
+    ) -> Result<usize, ApplicationError> {
+        check_index(i)?;
+        let script: Vec<ScriptEvent> = self.parse_script_text(text)?;
+        return self.run_script(i, script);
+    }
+
+    pub fn run_named_script(
+        &mut self, i: usize, name: &str
+    ) -> Result<usize, ApplicationError> {
+        check_index(i)?;
+        let script: Vec<ScriptEvent> = self.scripts.load(name)?;
+        return self.run_script(i, script);
+    }
+
+    pub fn get_macros(
+        &self, i: usize
+    ) -> Result<Vec<MacroBinding>, ApplicationError> {
+        check_index(i)?;
+        if let Ok(gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            return Ok(gamepad_manager.get_macros(i));
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    pub fn set_macro(
+        &mut self, i: usize, mut binding: MacroBinding
+    ) -> Result<(), ApplicationError> {
+        check_index(i)?;
+        if binding.trigger.len() == 0 {
+            return Err(ApplicationError::new(
+                ErrorCode::InvalidArgument,
+                "A macro needs at least one button to set it off."));
+        }
+        if let Some(name) = &binding.script_name {
+            binding.script = self.scripts.load(name)?;
+        }
+        if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            return gamepad_manager.set_macro(i, binding).map_err(|e|
+                ApplicationError::new(ErrorCode::InvalidArgument, &e));
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    pub fn clear_macro(
+        &mut self, i: usize, trigger: Vec<PhysicalButton>
+    ) -> Result<(), ApplicationError> {
+        check_index(i)?;
+        if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            if gamepad_manager.clear_macro(i, &trigger) {
+                return Ok(());
+            } else {
+                return Err(ApplicationError::new(
+                    ErrorCode::InvalidArgument,
+                    "There's no macro on those buttons."));
+            }
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    pub fn get_turbo(
+        &self, i: usize
+    ) -> Result<Vec<TurboButton>, ApplicationError> {
+        check_index(i)?;
+        if let Ok(gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            return Ok(gamepad_manager.get_turbo_map(i).get_buttons());
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    /// Gives a button turbo, or takes it away if there's no mode.
+    pub fn set_turbo(
+        &mut self, i: usize, button: GamepadButton,
+        turbo: Option<(TurboMode, usize)>
+    ) -> Result<(), ApplicationError> {
+        check_index(i)?;
+        if let Some((_, 0)) = turbo {
+            return Err(ApplicationError::new(
+                ErrorCode::InvalidArgument,
+                "Turbo buttons need to stay pressed for at least a frame."));
+        }
+        if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            let mut turbo_map: TurboMap = gamepad_manager.get_turbo_map(i);
+            turbo_map.set(button, turbo);
+            gamepad_manager.set_turbo_map(i, turbo_map);
+            return Ok(());
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    pub fn get_merge_policy(
+        &self, i: usize
+    ) -> Result<MergePolicy, ApplicationError> {
+        check_index(i)?;
+        if let Ok(gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            return Ok(gamepad_manager.get_merge_policy(i));
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    pub fn set_merge_policy(
+        &mut self, i: usize, merge_policy: MergePolicy
+    ) -> Result<(), ApplicationError> {
+        check_index(i)?;
+        if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            gamepad_manager.set_merge_policy(i, merge_policy);
+            return Ok(());
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    /// Merges whatever's driving gamepad j into gamepad i, leaving j empty.
+    /// The filters narrow down what each side gets to drive afterwards.
+    pub fn merge_gamepads(
+        &mut self, i: usize, j: usize, i_filter: InputFilter,
+        j_filter: InputFilter
+    ) -> Result<(), ApplicationError> {
+        check_index(i)?;
+        check_index(j)?;
+        if i == j {
+            return Err(ApplicationError::new(
+                ErrorCode::InvalidArgument,
+                "A gamepad can't be merged into itself."));
+        }
+        if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            if gamepad_manager.merge(i, j, i_filter, j_filter) {
+                return Ok(());
+            } else {
+                return Err(ApplicationError::new(
+                    ErrorCode::InvalidGamepad,
+                    "Both slots need a controller in them, and neither can \
+                    be half of a split controller."));
+            }
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    pub fn unmerge_gamepads(
+        &mut self, i: usize
+    ) -> Result<(), ApplicationError> {
+        check_index(i)?;
+        if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            gamepad_manager.unmerge(i);
+            return Ok(());
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    /// Splits the controller in slot i between slots i and j, as a sideways
+    /// left and right Joy-Con.
+    pub fn split_gamepad(
+        &mut self, i: usize, j: usize
+    ) -> Result<(), ApplicationError> {
+        check_index(i)?;
+        check_index(j)?;
+        if i == j {
+            return Err(ApplicationError::new(
+                ErrorCode::InvalidArgument,
+                "A gamepad can't be split into itself."));
+        }
+        if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            if gamepad_manager.split(i, j) {
+                return Ok(());
+            } else {
+                return Err(ApplicationError::new(
+                    ErrorCode::InvalidGamepad,
+                    "Only an unsplit controller can go into an empty slot."));
+            }
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    pub fn unsplit_gamepad(
+        &mut self, i: usize
+    ) -> Result<(), ApplicationError> {
+        check_index(i)?;
+        if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            if gamepad_manager.unsplit(i) {
+                return Ok(());
+            } else {
+                return Err(ApplicationError::new(
+                    ErrorCode::InvalidGamepad,
+                    "The controller in that slot isn't split."));
+            }
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    pub fn get_left_deadzone(&self, i: usize) -> Result<f32, ApplicationError> {
+        check_index(i)?;
         if let Ok(gamepad_manager) = self.gamepad_manager_mtx.lock() {
             return Ok(gamepad_manager.get_left_deadzone(i));
         } else {
-            return Err(String::from("Failed to lock gamepad manager."));
+            return Err(manager_lock_error());
         }
     }
 
     pub fn set_left_deadzone(
         &mut self, i: usize, deadzone: f32
-    ) -> Result<(), String> {
+    ) -> Result<(), ApplicationError> {
+        check_index(i)?;
         if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
             gamepad_manager.set_left_deadzone(i, deadzone);
             return Ok(());
         } else {
-            return Err(String::from("Failed to lock gamepad manager."));
+            return Err(manager_lock_error());
         }
     }
 
-    pub fn get_right_deadzone(&self, i: usize) -> Result<f32, String> {
+    pub fn get_right_deadzone(
+        &self, i: usize
+    ) -> Result<f32, ApplicationError> {
+        check_index(i)?;
         if let Ok(gamepad_manager) = self.gamepad_manager_mtx.lock() {
             return Ok(gamepad_manager.get_right_deadzone(i));
         } else {
-            return Err(String::from("Failed to lock gamepad manager."));
+            return Err(manager_lock_error());
         }
     }
 
     pub fn set_right_deadzone(
         &mut self, i: usize, deadzone: f32
-    ) -> Result<(), String> {
+    ) -> Result<(), ApplicationError> {
+        check_index(i)?;
         if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
             gamepad_manager.set_right_deadzone(i, deadzone);
             return Ok(());
         } else {
-            return Err(String::from("Failed to lock gamepad manager."));
+            return Err(manager_lock_error());
+        }
+    }
+
+    /// Captures the current settings as a profile.
+    pub fn get_profile(&self) -> Result<Profile, ApplicationError> {
+        let ips: Vec<String> = self.get_ips()?;
+        if let Ok(gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            let mut gamepads: Vec<GamepadSettings> = vec!();
+            for i in 0..NUM_GAMEPADS {
+                gamepads.push(gamepad_manager.get_settings(i));
+            }
+            return Ok(Profile {
+                ips: ips,
+                anarchy_mode: gamepad_manager.get_anarchy_mode(),
+                gamepads: gamepads,
+                devices: gamepad_manager.get_device_settings()
+            });
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    pub fn set_profile(
+        &mut self, profile: Profile
+    ) -> Result<(), ApplicationError> {
+        self.set_ips(profile.ips)?;
+        if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            gamepad_manager.set_anarchy_mode(profile.anarchy_mode);
+            gamepad_manager.set_device_settings(profile.devices);
+            // Slots that the profile leaves out are left as they are.
+            for (i, settings) in profile.gamepads.into_iter().enumerate() {
+                if i < NUM_GAMEPADS {
+                    gamepad_manager.set_settings(i, settings);
+                }
+            }
+            return Ok(());
+        } else {
+            return Err(manager_lock_error());
         }
     }
 
+    pub fn list_profiles(&self) -> Result<Vec<String>, ApplicationError> {
+        return self.profiles.list();
+    }
+
+    pub fn save_profile(&self, name: &str) -> Result<(), ApplicationError> {
+        let profile: Profile = self.get_profile()?;
+        return self.profiles.save(name, &profile);
+    }
+
+    pub fn load_profile(&mut self, name: &str) -> Result<(), ApplicationError> {
+        let profile: Profile = self.profiles.load(name)?;
+        return self.set_profile(profile);
+    }
+
+    /// Runs a script on a gamepad, returning the handle it goes by.
     pub fn run_script(
         &mut self, i: usize, script: Vec<ScriptEvent>
-    ) -> Result<(), String> {
+    ) -> Result<usize, ApplicationError> {
+        check_index(i)?;
         if self.state.get_connected().load(Ordering::Relaxed) {
             if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
-                gamepad_manager.run_script(i, script);
-                return Ok(());
+                return gamepad_manager.run_script(i, &script)
+                    .map_err(|e| ApplicationError::new(
+                        ErrorCode::InvalidArgument, &e));
             } else {
-                return Err(String::from("Failed to lock gamepad manager."));
+                return Err(manager_lock_error());
             }
         } else {
-            return Err(String::from("Cannot run script while disconnected."));
+            return Err(ApplicationError::new(
+                ErrorCode::NotConnected,
+                "Cannot run script while disconnected."));
         }
     }
 
-    pub fn swap(&mut self, i: usize, j: usize) -> Result<(), String> {
+    pub fn get_running_scripts(
+        &self
+    ) -> Result<Vec<ScriptStatus>, ApplicationError> {
+        if let Ok(gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            return Ok(gamepad_manager.get_running_scripts());
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    /// Stops a running script, letting go of whatever it was holding.
+    pub fn cancel_script(
+        &mut self, handle: usize
+    ) -> Result<(), ApplicationError> {
+        if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
+            if gamepad_manager.cancel_script(handle) {
+                return Ok(());
+            }
+            return Err(ApplicationError::new(
+                ErrorCode::ScriptNotRunning,
+                &format!("There's no script running with handle {}.", handle)));
+        } else {
+            return Err(manager_lock_error());
+        }
+    }
+
+    pub fn swap(&mut self, i: usize, j: usize) -> Result<(), ApplicationError> {
+        check_index(i)?;
+        check_index(j)?;
         if let Ok(mut gamepad_manager) = self.gamepad_manager_mtx.lock() {
             gamepad_manager.swap(i, j);
             return Ok(());
         } else {
-            return Err(String::from("Failed to lock gamepad manager."));
+            return Err(manager_lock_error());
         }
     }
 
-    pub fn connect(&mut self) -> Result<(), String> {
+    pub fn connect(&mut self) -> Result<(), ApplicationError> {
         if !self.state.get_connected().load(Ordering::Relaxed) {
             if let Ok(sys_if) = self.sys_if_mtx.lock() {
                 if sys_if.get_ips().len() == 0 {
-                    return Err(String::from("Cannot connect without any IPs."));
+                    return Err(ApplicationError::new(
+                        ErrorCode::NoIps, "Cannot connect without any IPs."));
                 } else {
                     self.state.set_connected(true);
                     return Ok(());
                 }
             } else {
-                return Err(String::from("Failed to lock sysmodule interface."));
+                return Err(sys_if_lock_error());
             }
         } else {
-            return Err(String::from("Already connected to sysmodule."));
+            return Err(ApplicationError::new(
+                ErrorCode::AlreadyConnected,
+                "Already connected to sysmodule."));
         }
     }
 
-    pub fn disconnect(&mut self) -> Result<(), String> {
+    pub fn disconnect(&mut self) -> Result<(), ApplicationError> {
         if self.state.get_connected().load(Ordering::Relaxed) {
             self.state.set_connected(false);
             return Ok(());
         } else {
-            return Err(String::from("Already disconnected from sysmodule."));
+            return Err(ApplicationError::new(
+                ErrorCode::AlreadyDisconnected,
+                "Already disconnected from sysmodule."));
         }
     }
 
-    pub fn exit(&mut self) -> Result<(), String> {
+    pub fn exit(&mut self) -> Result<(), ApplicationError> {
         self.state.set_connected(false);
         self.state.set_done(true);
-        return self.sdl_writer.exit();
+        let mut result: Result<(), ApplicationError> = Ok(());
+        // Every source gets stopped, even if one of them fails to.
+        for source in self.sources.iter_mut() {
+            if let Err(e) = source.stop() {
+                result = Err(ApplicationError::new(ErrorCode::ServerError, &e));
+            }
+        }
+        return result;
     }
 
     pub fn join(self) -> () {
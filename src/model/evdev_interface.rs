@@ -0,0 +1,628 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU32, Ordering}
+    },
+    thread
+};
+
+use crate::model::{
+    input::device_info::DeviceInfo,
+    input_source::{InputBuffer, InputSource}
+};
+use evdev::{
+    AbsoluteAxisType, Device, InputEvent, InputEventKind, InputId, Key
+};
+use inotify::{Inotify, WatchMask};
+use sdl_event_server::{SdlAxis, SdlButton, SdlEvent};
+
+fn to_button(key: Key) -> Option<SdlButton> {
+    match key {
+        Key::BTN_SOUTH => Some(SdlButton::A),
+        Key::BTN_EAST => Some(SdlButton::B),
+        // The kernel's north and west are SDL's Y and X respectively.
+        Key::BTN_WEST => Some(SdlButton::X),
+        Key::BTN_NORTH => Some(SdlButton::Y),
+        Key::BTN_SELECT => Some(SdlButton::Back),
+        Key::BTN_MODE => Some(SdlButton::Guide),
+        Key::BTN_START => Some(SdlButton::Start),
+        Key::BTN_THUMBL => Some(SdlButton::LeftStick),
+        Key::BTN_THUMBR => Some(SdlButton::RightStick),
+        Key::BTN_TL => Some(SdlButton::LeftShoulder),
+        Key::BTN_TR => Some(SdlButton::RightShoulder),
+        Key::BTN_DPAD_UP => Some(SdlButton::DPadUp),
+        Key::BTN_DPAD_DOWN => Some(SdlButton::DPadDown),
+        Key::BTN_DPAD_LEFT => Some(SdlButton::DPadLeft),
+        Key::BTN_DPAD_RIGHT => Some(SdlButton::DPadRight),
+        _ => None
+    }
+}
+
+/// Builds a GUID laid out the same way as SDL's, from the bus, vendor, product
+/// and version.
+///
+/// SDL also mixes in a checksum of the name, so this won't always match what
+/// the SDL server reports for the same controller.
+fn to_guid(id: InputId) -> String {
+    let words: [u16; 8] = [
+        id.bus_type().0, 0, id.vendor(), 0, id.product(), 0, id.version(), 0
+    ];
+    let mut guid: String = String::new();
+    for word in words {
+        for byte in word.to_le_bytes() {
+            guid.push_str(&format!("{:02x}", byte));
+        }
+    }
+    return guid;
+}
+
+/// Scales a value in the given range to somewhere between the two bounds.
+fn scale(value: i32, range: (i32, i32), bounds: (i32, i32)) -> i16 {
+    let (minimum, maximum): (i32, i32) = range;
+    if maximum <= minimum {
+        return 0;
+    }
+    let t: f32 = (value - minimum) as f32 / (maximum - minimum) as f32;
+    let scaled: f32
+        = bounds.0 as f32 + t * (bounds.1 as f32 - bounds.0 as f32);
+    return scaled.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+}
+
+/// Returns the SDL axis that an absolute axis is read as, if any. Z and RZ are
+/// the right stick on gamepads that put it there, and triggers otherwise.
+fn to_axis(abs: AbsoluteAxisType, z_is_stick: bool) -> Option<SdlAxis> {
+    match abs {
+        AbsoluteAxisType::ABS_X => Some(SdlAxis::LeftX),
+        AbsoluteAxisType::ABS_Y => Some(SdlAxis::LeftY),
+        AbsoluteAxisType::ABS_RX => Some(SdlAxis::RightX),
+        AbsoluteAxisType::ABS_RY => Some(SdlAxis::RightY),
+        AbsoluteAxisType::ABS_Z if z_is_stick => Some(SdlAxis::RightX),
+        AbsoluteAxisType::ABS_Z => Some(SdlAxis::TriggerLeft),
+        AbsoluteAxisType::ABS_RZ if z_is_stick => Some(SdlAxis::RightY),
+        AbsoluteAxisType::ABS_RZ => Some(SdlAxis::TriggerRight),
+        AbsoluteAxisType::ABS_BRAKE => Some(SdlAxis::TriggerLeft),
+        AbsoluteAxisType::ABS_GAS => Some(SdlAxis::TriggerRight),
+        _ => None
+    }
+}
+
+/// Whatever we need to remember to turn a gamepad's events into SDL's.
+struct EvdevMapping {
+    which: u32,
+    axes: HashMap<u16, (SdlAxis, (i32, i32))>,
+    // The last direction each hat axis pointed in.
+    hat: (i32, i32)
+}
+
+/// A single gamepad under /dev/input.
+struct EvdevGamepad {
+    device: Device,
+    mapping: EvdevMapping
+}
+
+impl EvdevGamepad {
+    /// Opens the device at the given path, if it's a gamepad.
+    fn open(path: &Path, which: u32) -> Option<EvdevGamepad> {
+        let device: Device = Device::open(path).ok()?;
+        if !device.supported_keys()?.contains(Key::BTN_SOUTH) {
+            return None;
+        }
+
+        let mut axes: HashMap<u16, (SdlAxis, (i32, i32))> = HashMap::new();
+        if let (Some(supported), Ok(state))
+            = (device.supported_absolute_axes(), device.get_abs_state()) {
+            // Some gamepads put their right stick on Z and RZ, but they won't
+            // have RX if they do.
+            let z_is_stick: bool
+                = !supported.contains(AbsoluteAxisType::ABS_RX);
+            for abs in supported.iter() {
+                if let Some(axis) = to_axis(abs, z_is_stick) {
+                    let info = state[abs.0 as usize];
+                    axes.insert(abs.0, (axis, (info.minimum, info.maximum)));
+                }
+            }
+        }
+
+        return Some(EvdevGamepad {
+            device: device,
+            mapping: EvdevMapping {
+                which: which,
+                axes: axes,
+                hat: (0, 0)
+            }
+        });
+    }
+
+    fn get_device_info(&self) -> DeviceInfo {
+        return DeviceInfo {
+            guid: to_guid(self.device.input_id()),
+            name: String::from(self.device.name().unwrap_or("")),
+            buttons: None
+        }
+    }
+
+    /// Reads events until the device goes away.
+    fn read_into(
+        &mut self, input: &InputBuffer, done: &AtomicBool
+    ) -> Result<(), String> {
+        while !done.load(Ordering::Relaxed) {
+            let events: Vec<InputEvent> = match self.device.fetch_events() {
+                Err(_) => return Err(String::from("Device was removed.")),
+                Ok(events) => events.collect()
+            };
+            for event in events {
+                for sdl_event in self.mapping.to_sdl(event) {
+                    input.push_event(sdl_event)?;
+                }
+            }
+        }
+        return Ok(());
+    }
+}
+
+impl EvdevMapping {
+    fn axis_motion(&self, axis: SdlAxis, value: i16) -> SdlEvent {
+        return SdlEvent::AxisMotion {
+            timestamp: 0,
+            which: self.which,
+            axis: axis,
+            value: value
+        }
+    }
+
+    fn button_press(&self, button: SdlButton, pressed: bool) -> SdlEvent {
+        return SdlEvent::ButtonPress {
+            timestamp: 0,
+            which: self.which,
+            button: button,
+            pressed: pressed
+        }
+    }
+
+    /// Turns a hat's new direction into D-pad presses and releases.
+    fn update_hat(
+        &mut self, vertical: bool, direction: i32
+    ) -> Vec<SdlEvent> {
+        let (negative, positive): (SdlButton, SdlButton) = if vertical {
+            (SdlButton::DPadUp, SdlButton::DPadDown)
+        } else {
+            (SdlButton::DPadLeft, SdlButton::DPadRight)
+        };
+        let previous: i32 = if vertical { self.hat.1 } else { self.hat.0 };
+        let direction: i32 = direction.signum();
+        if vertical {
+            self.hat.1 = direction;
+        } else {
+            self.hat.0 = direction;
+        }
+
+        let mut events: Vec<SdlEvent> = vec!();
+        if previous == direction {
+            return events;
+        }
+        match previous {
+            -1 => events.push(self.button_press(negative, false)),
+            1 => events.push(self.button_press(positive, false)),
+            _ => ()
+        }
+        match direction {
+            -1 => events.push(self.button_press(negative, true)),
+            1 => events.push(self.button_press(positive, true)),
+            _ => ()
+        }
+        return events;
+    }
+
+    fn to_sdl(&mut self, event: InputEvent) -> Vec<SdlEvent> {
+        match event.kind() {
+            // A value of 2 is the key repeating, which we don't care about.
+            InputEventKind::Key(key) if event.value() != 2 => {
+                let pressed: bool = event.value() == 1;
+                match key {
+                    // Gamepads with digital triggers just go all the way.
+                    Key::BTN_TL2 => vec!(self.axis_motion(
+                        SdlAxis::TriggerLeft,
+                        if pressed { i16::MAX } else { 0 })),
+                    Key::BTN_TR2 => vec!(self.axis_motion(
+                        SdlAxis::TriggerRight,
+                        if pressed { i16::MAX } else { 0 })),
+                    _ => match to_button(key) {
+                        None => vec!(),
+                        Some(button) => vec!(self.button_press(button, pressed))
+                    }
+                }
+            },
+            InputEventKind::AbsAxis(AbsoluteAxisType::ABS_HAT0X)
+                => self.update_hat(false, event.value()),
+            InputEventKind::AbsAxis(AbsoluteAxisType::ABS_HAT0Y)
+                => self.update_hat(true, event.value()),
+            InputEventKind::AbsAxis(abs) => match self.axes.get(&abs.0) {
+                None => vec!(),
+                Some((axis, range)) => {
+                    let bounds: (i32, i32) = if axis.is_trigger() {
+                        (0, i16::MAX as i32)
+                    } else {
+                        (i16::MIN as i32, i16::MAX as i32)
+                    };
+                    vec!(self.axis_motion(
+                        *axis, scale(event.value(), *range, bounds)))
+                }
+            },
+            _ => vec!()
+        }
+    }
+}
+
+/// Reads gamepads straight from evdev, so Linux doesn't need the SDL server.
+///
+/// New devices are picked up by watching the input directory with inotify.
+/// Anything that shows up there as a gamepad is used, including virtual ones
+/// made through uinput, which makes it easy to test with.
+pub struct EvdevSource {
+    directory: PathBuf,
+    done: Arc<AtomicBool>
+}
+
+impl EvdevSource {
+    pub fn new(directory: &Path, done: Arc<AtomicBool>) -> EvdevSource {
+        return EvdevSource {
+            directory: directory.to_path_buf(),
+            done: done
+        }
+    }
+}
+
+/// Calls the given function with every device in the input directory, then
+/// again with every device that shows up there afterwards.
+///
+/// Devices often show up before we're allowed to open them, so they'll also
+/// come through again once their permissions change. It's up to the function
+/// to ignore devices it already has open.
+pub fn watch_devices<F>(
+    directory: &Path, done: Arc<AtomicBool>, mut open: F
+) -> Result<(), String>
+where F: FnMut(PathBuf) -> () + Send + 'static {
+    let is_event_device = |path: &PathBuf| path.file_name()
+        .and_then(|name| name.to_str())
+        .map_or(false, |name| name.starts_with("event"));
+
+    let mut inotify: Inotify = Inotify::init()
+        .map_err(|e| format!("Failed to initialize inotify: {}", e))?;
+    // New devices are created, then have their permissions changed.
+    inotify.watches()
+        .add(directory, WatchMask::CREATE | WatchMask::ATTRIB)
+        .map_err(|e| format!("Failed to watch input directory: {}", e))?;
+
+    // The watch is made before looking at what's already there, so nothing
+    // can slip through in between.
+    let paths: Vec<PathBuf> = fs::read_dir(directory)
+        .map_err(|e| format!("Failed to read input directory: {}", e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(is_event_device)
+        .collect();
+    for path in paths {
+        open(path);
+    }
+
+    let directory: PathBuf = directory.to_path_buf();
+    thread::spawn(move || {
+        let mut buffer: [u8; 4096] = [0; 4096];
+        while !done.load(Ordering::Relaxed) {
+            let paths: Vec<PathBuf> = match inotify
+                .read_events_blocking(&mut buffer) {
+                Err(_) => break,
+                Ok(events) => events
+                    .filter_map(|event| event.name)
+                    .map(|name| directory.join(name))
+                    .filter(is_event_device)
+                    .collect()
+            };
+            for path in paths {
+                open(path);
+            }
+        }
+    });
+    return Ok(());
+}
+
+/// Opens the device at the given path on a thread of its own, unless it's
+/// already open or isn't a gamepad.
+fn open_device(
+    path: PathBuf, input: &InputBuffer, done: &Arc<AtomicBool>,
+    opened_mtx: &Arc<Mutex<HashSet<PathBuf>>>, next_which: &Arc<AtomicU32>
+) -> () {
+    if let Ok(mut opened) = opened_mtx.lock() {
+        if opened.contains(&path) {
+            return;
+        }
+        let which: u32 = next_which.load(Ordering::Relaxed);
+        let mut gamepad: EvdevGamepad = match EvdevGamepad::open(&path, which) {
+            None => return,
+            Some(gamepad) => gamepad
+        };
+        next_which.fetch_add(1, Ordering::Relaxed);
+        opened.insert(path.clone());
+
+        let device_input: InputBuffer = input.clone();
+        let device_done: Arc<AtomicBool> = Arc::clone(done);
+        let device_opened_mtx: Arc<Mutex<HashSet<PathBuf>>>
+            = Arc::clone(opened_mtx);
+        thread::spawn(move || {
+            let added: SdlEvent = SdlEvent::ControllerAdded {
+                timestamp: 0,
+                which: which
+            };
+            let result: Result<(), String> = device_input
+                .push_device(which, gamepad.get_device_info())
+                .and_then(|_| device_input.push_event(added))
+                .and_then(|_| gamepad.read_into(&device_input, &device_done));
+            if let Err(_) = result {
+                device_input.push_event(SdlEvent::ControllerRemoved {
+                    timestamp: 0,
+                    which: which
+                }).unwrap_or(());
+            }
+            if let Ok(mut opened) = device_opened_mtx.lock() {
+                opened.remove(&path);
+            }
+        });
+    }
+}
+
+impl InputSource for EvdevSource {
+    fn start(&mut self, input: InputBuffer) -> Result<(), String> {
+        let done: Arc<AtomicBool> = Arc::clone(&self.done);
+        let opened_mtx: Arc<Mutex<HashSet<PathBuf>>>
+            = Arc::new(Mutex::new(HashSet::new()));
+        let next_which: Arc<AtomicU32> = Arc::new(AtomicU32::new(0));
+        return watch_devices(
+            &self.directory,
+            Arc::clone(&self.done),
+            move |path| open_device(
+                path, &input, &done, &opened_mtx, &next_which));
+    }
+
+    fn stop(&mut self) -> Result<(), String> {
+        // Every thread checks if we're done, and they're all reading from
+        // something that'll go away with us anyway.
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::model::input_source::INPUT_DIRECTORY;
+    use evdev::{
+        uinput::{VirtualDevice, VirtualDeviceBuilder},
+        AbsInfo, AttributeSet, BusType, EventType, UinputAbsSetup
+    };
+
+    /// SDL events can't be compared directly, but their JSON can.
+    fn to_json(events: &[SdlEvent]) -> serde_json::Value {
+        return serde_json::to_value(events).unwrap();
+    }
+
+    fn key(key: Key, value: i32) -> InputEvent {
+        return InputEvent::new(EventType::KEY, key.code(), value);
+    }
+
+    fn abs(abs: AbsoluteAxisType, value: i32) -> InputEvent {
+        return InputEvent::new(EventType::ABSOLUTE, abs.0, value);
+    }
+
+    fn press(button: SdlButton, pressed: bool) -> SdlEvent {
+        return SdlEvent::ButtonPress {
+            timestamp: 0,
+            which: 7,
+            button: button,
+            pressed: pressed
+        }
+    }
+
+    fn motion(axis: SdlAxis, value: i16) -> SdlEvent {
+        return SdlEvent::AxisMotion {
+            timestamp: 0,
+            which: 7,
+            axis: axis,
+            value: value
+        }
+    }
+
+    /// A gamepad with an 8-bit left stick and a 10-bit left trigger.
+    fn mapping() -> EvdevMapping {
+        let mut axes: HashMap<u16, (SdlAxis, (i32, i32))> = HashMap::new();
+        axes.insert(AbsoluteAxisType::ABS_X.0, (SdlAxis::LeftX, (0, 255)));
+        axes.insert(
+            AbsoluteAxisType::ABS_Z.0, (SdlAxis::TriggerLeft, (0, 1023)));
+        return EvdevMapping {
+            which: 7,
+            axes: axes,
+            hat: (0, 0)
+        }
+    }
+
+    #[test]
+    fn maps_face_buttons_by_position() {
+        assert!(to_button(Key::BTN_SOUTH) == Some(SdlButton::A));
+        assert!(to_button(Key::BTN_EAST) == Some(SdlButton::B));
+        assert!(to_button(Key::BTN_WEST) == Some(SdlButton::X));
+        assert!(to_button(Key::BTN_NORTH) == Some(SdlButton::Y));
+        assert!(to_button(Key::BTN_MODE) == Some(SdlButton::Guide));
+        assert!(to_button(Key::BTN_TL2) == None);
+        assert!(to_button(Key::KEY_A) == None);
+    }
+
+    #[test]
+    fn maps_keys_to_presses() {
+        let mut mapping: EvdevMapping = mapping();
+        let mut events: Vec<SdlEvent> = mapping.to_sdl(key(Key::BTN_SOUTH, 1));
+        events.extend(mapping.to_sdl(key(Key::BTN_SOUTH, 2)));
+        events.extend(mapping.to_sdl(key(Key::BTN_SOUTH, 0)));
+        events.extend(mapping.to_sdl(key(Key::KEY_A, 1)));
+        assert_eq!(to_json(&events), to_json(&[
+            press(SdlButton::A, true),
+            press(SdlButton::A, false)
+        ]));
+    }
+
+    #[test]
+    fn maps_digital_triggers_to_axes() {
+        let mut mapping: EvdevMapping = mapping();
+        let mut events: Vec<SdlEvent> = mapping.to_sdl(key(Key::BTN_TR2, 1));
+        events.extend(mapping.to_sdl(key(Key::BTN_TR2, 0)));
+        assert_eq!(to_json(&events), to_json(&[
+            motion(SdlAxis::TriggerRight, i16::MAX),
+            motion(SdlAxis::TriggerRight, 0)
+        ]));
+    }
+
+    #[test]
+    fn scales_axes_to_their_bounds() {
+        let mut mapping: EvdevMapping = mapping();
+        let mut events: Vec<SdlEvent> = vec!();
+        for (axis, value) in [
+            (AbsoluteAxisType::ABS_X, 0),
+            (AbsoluteAxisType::ABS_X, 255),
+            (AbsoluteAxisType::ABS_Z, 0),
+            (AbsoluteAxisType::ABS_Z, 1023),
+            // Axes that weren't found when opening the device are ignored.
+            (AbsoluteAxisType::ABS_RY, 100)
+        ] {
+            events.extend(mapping.to_sdl(abs(axis, value)));
+        }
+        assert_eq!(to_json(&events), to_json(&[
+            motion(SdlAxis::LeftX, i16::MIN),
+            motion(SdlAxis::LeftX, i16::MAX),
+            motion(SdlAxis::TriggerLeft, 0),
+            motion(SdlAxis::TriggerLeft, i16::MAX)
+        ]));
+        assert_eq!(scale(5, (5, 5), (0, 100)), 0);
+        assert_eq!(scale(500, (0, 255), (0, i16::MAX as i32)), i16::MAX);
+    }
+
+    #[test]
+    fn maps_z_to_the_right_stick_without_rx() {
+        assert!(to_axis(AbsoluteAxisType::ABS_Z, true)
+            .map_or(false, |axis| axis as usize == SdlAxis::RightX as usize));
+        assert!(to_axis(AbsoluteAxisType::ABS_RZ, true)
+            .map_or(false, |axis| axis as usize == SdlAxis::RightY as usize));
+        assert!(to_axis(AbsoluteAxisType::ABS_Z, false).map_or(
+            false, |axis| axis as usize == SdlAxis::TriggerLeft as usize));
+        assert!(to_axis(AbsoluteAxisType::ABS_HAT0X, false).is_none());
+    }
+
+    #[test]
+    fn maps_hats_to_the_dpad() {
+        let mut mapping: EvdevMapping = mapping();
+        let mut events: Vec<SdlEvent> = vec!();
+        for (axis, value) in [
+            (AbsoluteAxisType::ABS_HAT0Y, -1),
+            // Repeating a direction doesn't press it again.
+            (AbsoluteAxisType::ABS_HAT0Y, -1),
+            (AbsoluteAxisType::ABS_HAT0X, 1),
+            // Going straight to the other side releases the first one.
+            (AbsoluteAxisType::ABS_HAT0Y, 1),
+            (AbsoluteAxisType::ABS_HAT0Y, 0),
+            (AbsoluteAxisType::ABS_HAT0X, 0)
+        ] {
+            events.extend(mapping.to_sdl(abs(axis, value)));
+        }
+        assert_eq!(to_json(&events), to_json(&[
+            press(SdlButton::DPadUp, true),
+            press(SdlButton::DPadRight, true),
+            press(SdlButton::DPadUp, false),
+            press(SdlButton::DPadDown, true),
+            press(SdlButton::DPadDown, false),
+            press(SdlButton::DPadRight, false)
+        ]));
+    }
+
+    #[test]
+    fn builds_guids_like_sdl() {
+        // An Xbox 360 controller, which SDL knows by the same GUID.
+        let id: InputId
+            = InputId::new(BusType::BUS_USB, 0x045e, 0x028e, 0x0110);
+        assert_eq!(to_guid(id), "030000005e0400008e02000010010000");
+    }
+
+    /// Waits for the buffer to have an event that matches, and returns it.
+    fn wait_for<F>(
+        input: &InputBuffer, devices: &mut Vec<(u32, DeviceInfo)>, mut f: F
+    ) -> Option<SdlEvent>
+    where F: FnMut(&SdlEvent, &Vec<(u32, DeviceInfo)>) -> bool {
+        let deadline: Instant = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            let (events, new_devices) = input.take();
+            devices.extend(new_devices);
+            for event in events {
+                if f(&event, devices) {
+                    return Some(event);
+                }
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        return None;
+    }
+
+    /// Plugs in a gamepad through uinput, presses a button on it, then
+    /// unplugs it again. This needs /dev/uinput, which usually means root.
+    #[test]
+    #[ignore]
+    fn picks_up_uinput_gamepads() {
+        let name: &str = "vhid_companion test gamepad";
+        let mut keys: AttributeSet<Key> = AttributeSet::new();
+        keys.insert(Key::BTN_SOUTH);
+        keys.insert(Key::BTN_EAST);
+        let device: VirtualDevice = VirtualDeviceBuilder::new()
+            .and_then(|builder| builder.name(name).with_keys(&keys))
+            .and_then(|builder| builder.with_absolute_axis(
+                &UinputAbsSetup::new(
+                    AbsoluteAxisType::ABS_X,
+                    AbsInfo::new(0, -100, 100, 0, 0, 0))))
+            .and_then(|builder| builder.build())
+            .expect("Failed to make a uinput device.");
+
+        let (events_tx, _events_rx) = crossbeam_channel::unbounded();
+        let input: InputBuffer = InputBuffer::new(events_tx);
+        let done: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        let mut source: EvdevSource
+            = EvdevSource::new(Path::new(INPUT_DIRECTORY), Arc::clone(&done));
+        source.start(input.clone()).unwrap();
+
+        // Other gamepads might be plugged in too, so ours goes by its name.
+        let mut devices: Vec<(u32, DeviceInfo)> = vec!();
+        let added: Option<SdlEvent> = wait_for(
+            &input, &mut devices, |event, devices| match event {
+                SdlEvent::ControllerAdded { timestamp: _, which }
+                    => devices.iter().any(|(device, info)|
+                        device == which && info.name == name),
+                _ => false
+            });
+        let which: u32 = match added {
+            Some(SdlEvent::ControllerAdded { timestamp: _, which }) => which,
+            _ => panic!("The gamepad never showed up.")
+        };
+
+        let mut device: VirtualDevice = device;
+        device.emit(&[key(Key::BTN_SOUTH, 1)]).unwrap();
+        assert!(wait_for(&input, &mut devices, |event, _| match event {
+            SdlEvent::ButtonPress { timestamp: _, which: w, button, pressed }
+                => *w == which && *button == SdlButton::A && *pressed,
+            _ => false
+        }).is_some());
+
+        drop(device);
+        assert!(wait_for(&input, &mut devices, |event, _| match event {
+            SdlEvent::ControllerRemoved { timestamp: _, which: w }
+                => *w == which,
+            _ => false
+        }).is_some());
+        done.store(true, Ordering::Relaxed);
+    }
+}
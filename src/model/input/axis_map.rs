@@ -0,0 +1,61 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+/// What moving a stick does.
+pub enum StickMapping {
+    Stick,
+    // Presses the D-pad in whichever directions the stick is pushed.
+    DPad
+}
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+/// What pressing the D-pad does.
+pub enum DPadMapping {
+    DPad,
+    // Pushes the stick in whichever directions the D-pad is pressed.
+    LeftStick,
+    RightStick
+}
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+/// How a gamepad's sticks, D-pad and triggers turn into each other.
+///
+/// This comes before the button map, so a D-pad that drives a stick isn't
+/// also pressing whatever its buttons are mapped to. Thresholds and magnitudes
+/// are fractions of how far a stick or trigger can go, like deadzones.
+pub struct AxisMap {
+    pub left_stick: StickMapping,
+    pub right_stick: StickMapping,
+    /// How far a stick has to be pushed in a direction before it presses the
+    /// D-pad that way.
+    pub stick_threshold: f32,
+    pub dpad: DPadMapping,
+    /// How far the D-pad pushes a stick.
+    pub dpad_magnitude: f32,
+    /// How far each trigger has to be pressed before whatever it's mapped to
+    /// is pressed.
+    pub left_trigger_threshold: f32,
+    pub right_trigger_threshold: f32
+}
+
+impl Default for AxisMap {
+    fn default() -> AxisMap {
+        return AxisMap {
+            left_stick: StickMapping::Stick,
+            right_stick: StickMapping::Stick,
+            stick_threshold: 0.5,
+            dpad: DPadMapping::DPad,
+            dpad_magnitude: 1.0,
+            left_trigger_threshold: 0.5,
+            right_trigger_threshold: 0.5
+        }
+    }
+}
+
+impl AxisMap {
+    /// Turns a fraction into how far along an axis that is.
+    pub fn to_axis_value(fraction: f32) -> f32 {
+        return fraction.clamp(0.0, 1.0) * i16::MAX as f32;
+    }
+}
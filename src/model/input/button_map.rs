@@ -0,0 +1,252 @@
+use crate::model::input::gamepad::{GamepadButton, GamepadType};
+use sdl_event_server::{SdlAxis, SdlButton};
+use serde::{Serialize, Deserialize};
+
+const NUM_PHYSICAL_BUTTONS: usize = 23;
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+/// Everything on a physical controller that can be mapped to a button, which
+/// is every SDL button, along with both triggers.
+pub enum PhysicalButton {
+    A, B, X, Y, Back, Guide, Start, LeftStick, RightStick, LeftShoulder,
+    RightShoulder, DPadUp, DPadDown, DPadLeft, DPadRight, Misc1, Paddle1,
+    Paddle2, Paddle3, Paddle4, Touchpad, TriggerLeft, TriggerRight
+}
+
+impl PhysicalButton {
+    pub const ALL: [PhysicalButton; NUM_PHYSICAL_BUTTONS] = [
+        Self::A, Self::B, Self::X, Self::Y, Self::Back, Self::Guide,
+        Self::Start, Self::LeftStick, Self::RightStick, Self::LeftShoulder,
+        Self::RightShoulder, Self::DPadUp, Self::DPadDown, Self::DPadLeft,
+        Self::DPadRight, Self::Misc1, Self::Paddle1, Self::Paddle2,
+        Self::Paddle3, Self::Paddle4, Self::Touchpad, Self::TriggerLeft,
+        Self::TriggerRight
+    ];
+
+    pub fn from_sdl(button: SdlButton) -> PhysicalButton {
+        match button {
+            SdlButton::A => Self::A,
+            SdlButton::B => Self::B,
+            SdlButton::X => Self::X,
+            SdlButton::Y => Self::Y,
+            SdlButton::Back => Self::Back,
+            SdlButton::Guide => Self::Guide,
+            SdlButton::Start => Self::Start,
+            SdlButton::LeftStick => Self::LeftStick,
+            SdlButton::RightStick => Self::RightStick,
+            SdlButton::LeftShoulder => Self::LeftShoulder,
+            SdlButton::RightShoulder => Self::RightShoulder,
+            SdlButton::DPadUp => Self::DPadUp,
+            SdlButton::DPadDown => Self::DPadDown,
+            SdlButton::DPadLeft => Self::DPadLeft,
+            SdlButton::DPadRight => Self::DPadRight,
+            SdlButton::Misc1 => Self::Misc1,
+            SdlButton::Paddle1 => Self::Paddle1,
+            SdlButton::Paddle2 => Self::Paddle2,
+            SdlButton::Paddle3 => Self::Paddle3,
+            SdlButton::Paddle4 => Self::Paddle4,
+            SdlButton::Touchpad => Self::Touchpad
+        }
+    }
+
+    pub fn from_trigger(trigger: SdlAxis) -> Result<PhysicalButton, String> {
+        match trigger {
+            SdlAxis::TriggerLeft => Ok(Self::TriggerLeft),
+            SdlAxis::TriggerRight => Ok(Self::TriggerRight),
+            _ => Err(String::from("Only triggers can be mapped to buttons."))
+        }
+    }
+
+    /// Returns the buttons that this presses on the given type of gamepad if
+    /// it hasn't been remapped.
+    ///
+    /// These go by where buttons are rather than what they're labelled, so
+    /// the bottom face button (A on an Xbox controller) is the Switch's B.
+    /// Anything the gamepad doesn't have isn't mapped to anything.
+    pub fn get_default(&self, gamepad_type: GamepadType) -> Vec<GamepadButton> {
+        match gamepad_type {
+            GamepadType::SidewaysLeftJoyCon => self.get_left_joycon_default(),
+            GamepadType::SidewaysRightJoyCon
+                => self.get_right_joycon_default(),
+            _ => self.get_pro_controller_default()
+        }
+    }
+
+    fn get_pro_controller_default(&self) -> Vec<GamepadButton> {
+        match self {
+            Self::A => vec!(GamepadButton::B),
+            Self::B => vec!(GamepadButton::A),
+            Self::X => vec!(GamepadButton::Y),
+            Self::Y => vec!(GamepadButton::X),
+            Self::Back => vec!(GamepadButton::Minus),
+            Self::Guide => vec!(GamepadButton::Home),
+            Self::Start => vec!(GamepadButton::Plus),
+            Self::LeftStick => vec!(GamepadButton::LST),
+            Self::RightStick => vec!(GamepadButton::RST),
+            Self::LeftShoulder => vec!(GamepadButton::L),
+            Self::RightShoulder => vec!(GamepadButton::R),
+            Self::DPadUp => vec!(GamepadButton::DU),
+            Self::DPadDown => vec!(GamepadButton::DD),
+            Self::DPadLeft => vec!(GamepadButton::DL),
+            Self::DPadRight => vec!(GamepadButton::DR),
+            Self::Misc1 => vec!(GamepadButton::Capture),
+            Self::TriggerLeft => vec!(GamepadButton::ZL),
+            Self::TriggerRight => vec!(GamepadButton::ZR),
+            _ => vec!()
+        }
+    }
+
+    /// A sideways Joy-Con is turned a quarter of the way around, so each face
+    /// button is whichever of its own buttons ends up in that spot. The left
+    /// one is turned counterclockwise, putting its right button on top.
+    ///
+    /// The D-pad does the same as the face buttons, so either half of a
+    /// controller can be used on its own.
+    fn get_left_joycon_default(&self) -> Vec<GamepadButton> {
+        match self {
+            Self::A | Self::DPadDown => vec!(GamepadButton::DL),
+            Self::B | Self::DPadRight => vec!(GamepadButton::DD),
+            Self::X | Self::DPadLeft => vec!(GamepadButton::DU),
+            Self::Y | Self::DPadUp => vec!(GamepadButton::DR),
+            Self::Back | Self::Start => vec!(GamepadButton::Minus),
+            Self::Misc1 => vec!(GamepadButton::Capture),
+            Self::LeftStick | Self::RightStick => vec!(GamepadButton::LST),
+            Self::LeftShoulder => vec!(GamepadButton::SLL),
+            Self::RightShoulder => vec!(GamepadButton::SRL),
+            Self::TriggerLeft => vec!(GamepadButton::L),
+            Self::TriggerRight => vec!(GamepadButton::ZL),
+            _ => vec!()
+        }
+    }
+
+    /// The right one is turned clockwise, putting its top button (X) on the
+    /// right.
+    fn get_right_joycon_default(&self) -> Vec<GamepadButton> {
+        match self {
+            Self::A | Self::DPadDown => vec!(GamepadButton::A),
+            Self::B | Self::DPadRight => vec!(GamepadButton::X),
+            Self::X | Self::DPadLeft => vec!(GamepadButton::B),
+            Self::Y | Self::DPadUp => vec!(GamepadButton::Y),
+            Self::Back | Self::Start => vec!(GamepadButton::Plus),
+            Self::Guide => vec!(GamepadButton::Home),
+            Self::LeftStick | Self::RightStick => vec!(GamepadButton::RST),
+            Self::LeftShoulder => vec!(GamepadButton::SLR),
+            Self::RightShoulder => vec!(GamepadButton::SRR),
+            Self::TriggerLeft => vec!(GamepadButton::R),
+            Self::TriggerRight => vec!(GamepadButton::ZR),
+            _ => vec!()
+        }
+    }
+
+    fn to_index(&self) -> usize {
+        return *self as usize;
+    }
+}
+
+/// Packs buttons into a mask by their order in GamepadButton::ALL.
+///
+/// These aren't the bits that get sent to the sysmodule, since some of those
+/// are shared (like Home and LR), and we need to tell every button apart.
+fn to_mask(buttons: &[GamepadButton]) -> u32 {
+    let mut mask: u32 = 0;
+    for button in buttons {
+        mask |= 1 << (*button as u32);
+    }
+    return mask;
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+/// What a single physical button presses. Mapping it to nothing unmaps it,
+/// while mapping it to more than one button presses all of them at once.
+pub struct ButtonMapping {
+    pub from: PhysicalButton,
+    pub to: Vec<GamepadButton>
+}
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Vec<ButtonMapping>", into = "Vec<ButtonMapping>")]
+/// What every physical button presses on a gamepad.
+///
+/// Each physical button gets a mask of the buttons it presses, so a map is
+/// cheap to copy around with its gamepad. When saved, only the buttons that
+/// differ from a Pro Controller's defaults are written out.
+pub struct ButtonMap {
+    masks: [u32; NUM_PHYSICAL_BUTTONS]
+}
+
+impl Default for ButtonMap {
+    fn default() -> ButtonMap {
+        return ButtonMap::for_type(GamepadType::SwitchProController);
+    }
+}
+
+impl ButtonMap {
+    /// Returns the map that the given type of gamepad starts out with.
+    pub fn for_type(gamepad_type: GamepadType) -> ButtonMap {
+        let mut masks: [u32; NUM_PHYSICAL_BUTTONS] = [0; NUM_PHYSICAL_BUTTONS];
+        for physical in PhysicalButton::ALL {
+            masks[physical.to_index()]
+                = to_mask(&physical.get_default(gamepad_type));
+        }
+        return ButtonMap {
+            masks: masks
+        }
+    }
+
+    /// Returns every button that the physical button presses, as a mask by
+    /// their order in GamepadButton::ALL.
+    pub fn get_mask(&self, physical: PhysicalButton) -> u32 {
+        return self.masks[physical.to_index()];
+    }
+
+    /// Returns every button that the physical button presses.
+    pub fn get(&self, physical: PhysicalButton) -> Vec<GamepadButton> {
+        let mask: u32 = self.get_mask(physical);
+        let mut buttons: Vec<GamepadButton> = vec!();
+        for button in GamepadButton::ALL {
+            if mask & (1 << (button as u32)) != 0 {
+                buttons.push(button);
+            }
+        }
+        return buttons;
+    }
+
+    pub fn set(
+        &mut self, physical: PhysicalButton, buttons: &[GamepadButton]
+    ) -> () {
+        self.masks[physical.to_index()] = to_mask(buttons);
+    }
+
+    /// Returns what every physical button presses, remapped or not.
+    pub fn get_mappings(&self) -> Vec<ButtonMapping> {
+        let mut mappings: Vec<ButtonMapping> = vec!();
+        for physical in PhysicalButton::ALL {
+            mappings.push(ButtonMapping {
+                from: physical,
+                to: self.get(physical)
+            });
+        }
+        return mappings;
+    }
+}
+
+impl From<Vec<ButtonMapping>> for ButtonMap {
+    fn from(mappings: Vec<ButtonMapping>) -> ButtonMap {
+        let mut map: ButtonMap = ButtonMap::default();
+        for mapping in mappings {
+            map.set(mapping.from, &mapping.to);
+        }
+        return map;
+    }
+}
+
+impl From<ButtonMap> for Vec<ButtonMapping> {
+    fn from(map: ButtonMap) -> Vec<ButtonMapping> {
+        let default: ButtonMap = ButtonMap::default();
+        return map.get_mappings()
+            .into_iter()
+            .filter(|mapping| map.masks[mapping.from.to_index()]
+                != default.masks[mapping.from.to_index()])
+            .collect();
+    }
+}
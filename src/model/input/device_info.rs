@@ -0,0 +1,29 @@
+use crate::model::input::button_map::ButtonMap;
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+/// Identifies a physical controller, as reported by its input source when it's
+/// added. The SDL server reports SDL's own GUID and name for it.
+///
+/// GUIDs identify the kind of controller rather than the exact one, so two
+/// identical controllers will share their settings.
+pub struct DeviceInfo {
+    pub guid: String,
+    pub name: String,
+    /// What the device's buttons press no matter how its gamepad is mapped,
+    /// for devices that decide that for themselves, like a keyboard.
+    #[serde(skip)]
+    pub buttons: Option<ButtonMap>
+}
+
+impl DeviceInfo {
+    /// Returns what this device's settings are stored under, which is its GUID
+    /// if its source gave us one, and its name otherwise.
+    pub fn get_key(&self) -> String {
+        if self.guid.len() != 0 {
+            return self.guid.clone();
+        } else {
+            return self.name.clone();
+        }
+    }
+}
@@ -1,8 +1,14 @@
-use sdl_event_server::{SdlAxis, SdlButton, SdlEvent};
+use std::f32::consts::FRAC_1_SQRT_2;
 
-const TRIGGER_ACTUATION: i16 = i16::MAX;
+use crate::model::input::{
+    axis_map::{AxisMap, DPadMapping, StickMapping},
+    button_map::{ButtonMap, PhysicalButton},
+    turbo::{TurboMap, TurboMode}
+};
+use sdl_event_server::{SdlAxis, SdlEvent};
+use serde::{Serialize, Deserialize};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum GamepadType {
     Disconnected,
     SwitchProController,
@@ -10,12 +16,36 @@ pub enum GamepadType {
     SidewaysRightJoyCon
 }
 
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum GamepadButton {
     A, B, X, Y, LST, RST, L, R, ZL, ZR, Plus, Minus, DL, DU, DR, DD, LL, LU, LR,
     LD, RL, RU, RR, RD, SLL, SRL, SLR, SRR, Home, Capture
 }
 
 impl GamepadButton {
+    pub const ALL: [GamepadButton; 30] = [
+        Self::A, Self::B, Self::X, Self::Y, Self::LST, Self::RST, Self::L,
+        Self::R, Self::ZL, Self::ZR, Self::Plus, Self::Minus, Self::DL,
+        Self::DU, Self::DR, Self::DD, Self::LL, Self::LU, Self::LR, Self::LD,
+        Self::RL, Self::RU, Self::RR, Self::RD, Self::SLL, Self::SRL,
+        Self::SLR, Self::SRR, Self::Home, Self::Capture
+    ];
+
+    /// Decodes a mask of buttons, by their order in ALL, into every button
+    /// that's in it.
+    pub fn from_mask(mask: u32) -> Vec<GamepadButton> {
+        return Self::ALL
+            .into_iter()
+            .filter(|button| mask & (1 << (*button as u32)) != 0)
+            .collect();
+    }
+
+    /// Returns the bit this button is sent as.
+    ///
+    /// The sysmodule hands these to the Switch as-is, which takes bits 18 and
+    /// 19 to be Home and Capture. The stick directions that share them (LR
+    /// and LD) are worked out from the sticks on the Switch's end, so
+    /// pressing them here is the same as pressing Home or Capture.
     pub fn to_bit(&self) -> i32 {
         match self {
             Self::A => return 1,
@@ -50,31 +80,16 @@ impl GamepadButton {
             Self::Capture => return 1 << 19
         }
     }
+}
 
-    pub fn from_sdl(
-        button: SdlButton, gamepad_type: &GamepadType
-    ) -> Result<GamepadButton, String> {
-        match button {
-            // TODO: Map gamepad types later.
-            SdlButton::A => Ok(Self::B),
-            SdlButton::B => Ok(Self::A),
-            SdlButton::X => Ok(Self::Y),
-            SdlButton::Y => Ok(Self::X),
-            SdlButton::Back => Ok(Self::Minus),
-            SdlButton::Misc1 => Ok(Self::Capture),
-            SdlButton::Guide => Ok(Self::Home),
-            SdlButton::Start => Ok(Self::Plus),
-            SdlButton::LeftStick => Ok(Self::LST),
-            SdlButton::RightStick => Ok(Self::RST),
-            SdlButton::LeftShoulder => Ok(Self::L),
-            SdlButton::RightShoulder => Ok(Self::R),
-            SdlButton::DPadUp => Ok(Self::DU),
-            SdlButton::DPadDown => Ok(Self::DD),
-            SdlButton::DPadLeft => Ok(Self::DL),
-            SdlButton::DPadRight => Ok(Self::DR),
-            _ => Err(String::from("Button is unmapped."))
-        }
-    }
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+/// How a gamepad combines every controller that's been merged into it.
+pub enum MergePolicy {
+    // Buttons are held if any controller holds them, and sticks add up.
+    Sum,
+    // Buttons are the same as with Sum, but each stick goes wherever the
+    // controller pushing it the furthest says.
+    Furthest
 }
 
 #[derive(Copy, Clone)]
@@ -93,9 +108,10 @@ impl AnalogStick {
 
     pub fn get_position(&self) -> (i16, i16) {
         // I tried my best to make this look somewhat reasonable.
+        // Squaring has to happen as floats, since an i16 squared overflows.
         if f32::sqrt(
-            ((self.position.0.pow(2) as i32)
-             + (self.position.1.pow(2) as i32)) as f32)
+            (self.position.0 as f32).powi(2)
+            + (self.position.1 as f32).powi(2))
             <= self.deadzone * (i16::MAX as f32) {
             return (0, 0);
         } else {
@@ -103,6 +119,11 @@ impl AnalogStick {
         }
     }
 
+    /// Returns this stick's position without the deadzone applied.
+    pub fn get_raw_position(&self) -> (i16, i16) {
+        return self.position;
+    }
+
     pub fn get_deadzone(&self) -> f32 {
         return self.deadzone;
     }
@@ -137,16 +158,80 @@ impl AnalogStick {
             i16::saturating_add(self.position.1, other.get_position().1)
         );
     }
+
+    /// Takes the other stick's position if it's pushed further than this one.
+    pub fn merge_furthest(&mut self, other: AnalogStick) -> () {
+        let distance = |(x, y): (i16, i16)| (x as f32).hypot(y as f32);
+        if distance(other.get_position()) > distance(self.get_position()) {
+            self.position = other.get_position();
+        }
+    }
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+/// Everything about a gamepad that a user can tweak, as opposed to its state.
+pub struct GamepadSettings {
+    pub delay: usize,
+    pub left_deadzone: f32,
+    pub right_deadzone: f32,
+    pub buttons: ButtonMap,
+    pub axes: AxisMap,
+    pub merge_policy: MergePolicy,
+    pub turbo: TurboMap
+}
+
+impl Default for GamepadSettings {
+    fn default() -> GamepadSettings {
+        return GamepadSettings {
+            delay: 0,
+            left_deadzone: 0.0,
+            right_deadzone: 0.0,
+            buttons: ButtonMap::default(),
+            axes: AxisMap::default(),
+            merge_policy: MergePolicy::Sum,
+            turbo: TurboMap::default()
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
 pub struct Gamepad {
     gamepad_type: GamepadType,
     delay: usize,
-
-    buttons: i32,
+    button_map: ButtonMap,
+    // What the device driving this says its buttons press, which it gets to
+    // decide over the button map above.
+    device_buttons: Option<ButtonMap>,
+    axis_map: AxisMap,
+    merge_policy: MergePolicy,
+    turbo_map: TurboMap,
+
+    // Buttons are kept by their order in GamepadButton::ALL rather than as
+    // the bits they're sent as, since some of those bits are shared.
+    buttons: u32,
+    // The physical buttons that are held, as a mask by their order in
+    // PhysicalButton::ALL, and the buttons they press through the button map,
+    // by order in GamepadButton::ALL. More than one physical button can press
+    // the same button, so it's only let go of once none of them are held.
+    held: u32,
+    pressed: u32,
+    // The D-pad directions each stick is pushed in, for when it presses the
+    // D-pad, as masks like the one above. These get pressed along with
+    // everything else that's held.
+    stick_dpad: [u32; 2],
     left_stick: AnalogStick,
-    right_stick: AnalogStick 
+    right_stick: AnalogStick,
+    // Which physical D-pad directions are held, for when it drives a stick.
+    dpad: (bool, bool, bool, bool),
+    // Turbo buttons never go into the buttons above. These are masks by
+    // order in GamepadButton::ALL, like in a button map, of the ones that are
+    // held and the ones that are firing, along with the frame each one
+    // started firing on.
+    turbo_held: u32,
+    turbo_firing: u32,
+    turbo_started: [usize; 30],
+    frame: usize
 }
 
 impl Gamepad {
@@ -154,10 +239,23 @@ impl Gamepad {
         return Gamepad {
             gamepad_type: GamepadType::Disconnected,
             delay: 0,
+            button_map: ButtonMap::default(),
+            device_buttons: None,
+            axis_map: AxisMap::default(),
+            merge_policy: MergePolicy::Sum,
+            turbo_map: TurboMap::default(),
 
             buttons: 0,
+            held: 0,
+            pressed: 0,
+            stick_dpad: [0; 2],
             left_stick: AnalogStick::new(),
-            right_stick: AnalogStick::new()
+            right_stick: AnalogStick::new(),
+            dpad: (false, false, false, false),
+            turbo_held: 0,
+            turbo_firing: 0,
+            turbo_started: [0; 30],
+            frame: 0
         }
     }
 
@@ -173,8 +271,105 @@ impl Gamepad {
         self.delay = delay;
     }
 
+    pub fn get_button_map(&self) -> ButtonMap {
+        return self.button_map;
+    }
+
+    /// Also lets go of every button, since whatever's held down might not be
+    /// mapped to the same buttons anymore.
+    pub fn set_button_map(&mut self, button_map: ButtonMap) -> () {
+        if button_map != self.button_map {
+            self.button_map = button_map;
+            self.release_buttons();
+        }
+    }
+
+    /// Lets the device driving this decide what its buttons press, or leaves
+    /// it to the button map with None. Also lets go of every button, like
+    /// changing the button map does.
+    pub fn set_device_buttons(&mut self, buttons: Option<ButtonMap>) -> () {
+        if buttons != self.device_buttons {
+            self.device_buttons = buttons;
+            self.release_buttons();
+        }
+    }
+
+    pub fn get_axis_map(&self) -> AxisMap {
+        return self.axis_map;
+    }
+
+    /// Also lets go of everything the old mapping might've been holding.
+    pub fn set_axis_map(&mut self, axis_map: AxisMap) -> () {
+        if axis_map != self.axis_map {
+            self.axis_map = axis_map;
+            self.release_all();
+        }
+    }
+
+    pub fn get_merge_policy(&self) -> MergePolicy {
+        return self.merge_policy;
+    }
+
+    pub fn set_merge_policy(&mut self, merge_policy: MergePolicy) -> () {
+        self.merge_policy = merge_policy;
+    }
+
+    pub fn get_turbo_map(&self) -> TurboMap {
+        return self.turbo_map;
+    }
+
+    /// Also lets go of every button, like changing the button map does.
+    pub fn set_turbo_map(&mut self, turbo_map: TurboMap) -> () {
+        if turbo_map != self.turbo_map {
+            self.turbo_map = turbo_map;
+            self.release_buttons();
+        }
+    }
+
+    /// Returns every turbo button that's firing, whether it's pressed on this
+    /// frame or not.
+    pub fn get_turbo_firing(&self) -> Vec<GamepadButton> {
+        return GamepadButton::from_mask(self.turbo_firing);
+    }
+
+    /// Returns the buttons to send, with turbo buttons pressed or released
+    /// depending on the frame.
     pub fn get_buttons(&self) -> i32 {
-        return self.buttons;
+        let mut bits: i32 = 0;
+        for button in self.get_pressed() {
+            bits |= button.to_bit();
+        }
+        return bits;
+    }
+
+    /// Returns every button that's pressed on this frame, turbo included.
+    /// Unlike the bits that get sent, these tell Home and LR apart.
+    pub fn get_pressed(&self) -> Vec<GamepadButton> {
+        return GamepadButton::from_mask(self.get_pressed_mask());
+    }
+
+    fn get_pressed_mask(&self) -> u32 {
+        let mut buttons: u32 = self.buttons;
+        for button in GamepadButton::ALL {
+            if self.turbo_firing & (1 << (button as u32)) == 0 {
+                continue;
+            }
+            if let Some((_, frames)) = self.turbo_map.get(button) {
+                let elapsed: usize = self.frame
+                    .wrapping_sub(self.turbo_started[button as usize]);
+                // Pressed for the first few frames, then released for as
+                // many, and so on.
+                if (elapsed / frames) % 2 == 0 {
+                    buttons |= 1 << (button as u32);
+                }
+            }
+        }
+        return buttons;
+    }
+
+    /// Moves on to the next frame, which is what turbo buttons go by.
+    pub fn tick(&mut self) -> () {
+        self.frame = self.frame.wrapping_add(1);
     }
 
     pub fn get_left_stick(&self) -> AnalogStick {
@@ -201,17 +396,53 @@ impl Gamepad {
         self.right_stick.set_deadzone(deadzone);
     }
 
+    pub fn get_settings(&self) -> GamepadSettings {
+        return GamepadSettings {
+            delay: self.delay,
+            left_deadzone: self.get_left_deadzone(),
+            right_deadzone: self.get_right_deadzone(),
+            buttons: self.button_map,
+            axes: self.axis_map,
+            merge_policy: self.merge_policy,
+            turbo: self.turbo_map
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: GamepadSettings) -> () {
+        self.set_delay(settings.delay);
+        self.set_left_deadzone(settings.left_deadzone);
+        self.set_right_deadzone(settings.right_deadzone);
+        self.set_button_map(settings.buttons);
+        self.set_axis_map(settings.axes);
+        self.set_merge_policy(settings.merge_policy);
+        self.set_turbo_map(settings.turbo);
+    }
+
     pub fn connect(&mut self, gamepad_type: GamepadType) -> () {
         self.gamepad_type = gamepad_type;
     }
 
+    /// Changes what this is emulating while it's connected, which also gives
+    /// it that type's button map and lets go of everything.
+    pub fn set_gamepad_type(&mut self, gamepad_type: GamepadType) -> () {
+        self.gamepad_type = gamepad_type;
+        self.set_button_map(ButtonMap::for_type(gamepad_type));
+        self.release_all();
+    }
+
     pub fn disconnect(&mut self) -> () {
         self.gamepad_type = GamepadType::Disconnected;
         self.delay = 0;
+        self.button_map = ButtonMap::default();
+        self.device_buttons = None;
+        self.axis_map = AxisMap::default();
+        self.merge_policy = MergePolicy::Sum;
+        self.turbo_map = TurboMap::default();
 
-        self.buttons = 0;
+        self.release_buttons();
         self.left_stick.reset();
         self.right_stick.reset();
+        self.dpad = (false, false, false, false);
     }
 
     pub fn update(&mut self, event: SdlEvent) -> Result<(), String> {
@@ -225,12 +456,10 @@ impl Gamepad {
                     return Ok(());
                 },
             SdlEvent::ButtonPress { timestamp: _, which: _, button, pressed }
-                => match GamepadButton::from_sdl(button, &self.gamepad_type) {
-                    Err(e) => Err(e),
-                    Ok(mapped) => {
-                        self.update_button(mapped, pressed);
-                        return Ok(());
-                    }
+                => {
+                    self.update_physical(
+                        PhysicalButton::from_sdl(button), pressed);
+                    return Ok(());
                 },
             _ => Err(String::from(
                     "Gamepad.update() doesn't support controller addition and \
@@ -239,6 +468,78 @@ impl Gamepad {
     }
 
     fn update_axis(&mut self, axis: SdlAxis, value: i16) -> Result<(), String> {
+        let (mapping, stick): (StickMapping, usize) = match axis {
+            SdlAxis::LeftX | SdlAxis::LeftY => (self.axis_map.left_stick, 0),
+            _ => (self.axis_map.right_stick, 1)
+        };
+        if let StickMapping::DPad = mapping {
+            let threshold: f32
+                = AxisMap::to_axis_value(self.axis_map.stick_threshold);
+            let (negative, positive): (GamepadButton, GamepadButton)
+                = match axis {
+                    SdlAxis::LeftX | SdlAxis::RightX
+                        => (GamepadButton::DL, GamepadButton::DR),
+                    SdlAxis::LeftY | SdlAxis::RightY
+                        => (GamepadButton::DU, GamepadButton::DD),
+                    _ => return Err(String::from(
+                        "Gamepad.update_axis() doesn't support triggers."))
+                };
+            // Only this axis's directions change, and only on this stick.
+            let (negative, positive): (u32, u32)
+                = (1 << (negative as u32), 1 << (positive as u32));
+            let mut directions: u32
+                = self.stick_dpad[stick] & !(negative | positive);
+            if (value as f32) < -threshold {
+                directions |= negative;
+            } else if (value as f32) > threshold {
+                directions |= positive;
+            }
+            self.stick_dpad[stick] = directions;
+            self.update_pressed();
+            return Ok(());
+        }
+
+        // A sideways Joy-Con only has the one stick, which is turned the same
+        // way its buttons are, so either stick gets turned to match. That way
+        // a controller that's been split in half can use its right stick.
+        match (self.gamepad_type, axis) {
+            (
+                GamepadType::SidewaysLeftJoyCon,
+                SdlAxis::LeftX | SdlAxis::RightX
+            ) => {
+                self.left_stick.update(SdlAxis::LeftY, value).unwrap();
+                return Ok(());
+            },
+            (
+                GamepadType::SidewaysLeftJoyCon,
+                SdlAxis::LeftY | SdlAxis::RightY
+            ) => {
+                self.left_stick
+                    .update(SdlAxis::LeftX, value.saturating_neg())
+                    .unwrap();
+                return Ok(());
+            },
+            (
+                GamepadType::SidewaysRightJoyCon,
+                SdlAxis::LeftX | SdlAxis::RightX
+            ) => {
+                self.right_stick
+                    .update(SdlAxis::RightY, value.saturating_neg())
+                    .unwrap();
+                return Ok(());
+            },
+            (
+                GamepadType::SidewaysRightJoyCon,
+                SdlAxis::LeftY | SdlAxis::RightY
+            ) => {
+                self.right_stick.update(SdlAxis::RightX, value).unwrap();
+                return Ok(());
+            },
+            (GamepadType::SidewaysLeftJoyCon, _)
+            | (GamepadType::SidewaysRightJoyCon, _) => return Ok(()),
+            _ => ()
+        }
+
         match axis {
             SdlAxis::LeftX | SdlAxis::LeftY => {
                 self.left_stick.update(axis, value).unwrap();
@@ -256,18 +557,80 @@ impl Gamepad {
     fn update_trigger(
         &mut self, trigger: SdlAxis, value: i16
     ) -> Result<(), String> {
-        match trigger {
-            SdlAxis::TriggerLeft => {
-                self.update_button(GamepadButton::ZL, value > TRIGGER_ACTUATION);
-                return Ok(());
-            },
-            SdlAxis::TriggerRight => {
-                self.update_button(GamepadButton::ZR, value > TRIGGER_ACTUATION);
-                return Ok(());
-            },
-            _ => Err(String::from(
-                    "Gamepad.update_trigger() doesn't support axes."))
+        let physical: PhysicalButton = PhysicalButton::from_trigger(trigger)?;
+        let threshold: f32 = AxisMap::to_axis_value(match physical {
+            PhysicalButton::TriggerLeft => self.axis_map.left_trigger_threshold,
+            _ => self.axis_map.right_trigger_threshold
+        });
+        self.update_physical(physical, (value as f32) > threshold);
+        return Ok(());
+    }
+
+    /// Holds or lets go of a physical button, then presses or releases
+    /// whatever that changes.
+    fn update_physical(
+        &mut self, physical: PhysicalButton, pressed: bool
+    ) -> () {
+        if self.axis_map.dpad != DPadMapping::DPad {
+            let held: Option<&mut bool> = match physical {
+                PhysicalButton::DPadUp => Some(&mut self.dpad.0),
+                PhysicalButton::DPadDown => Some(&mut self.dpad.1),
+                PhysicalButton::DPadLeft => Some(&mut self.dpad.2),
+                PhysicalButton::DPadRight => Some(&mut self.dpad.3),
+                _ => None
+            };
+            if let Some(held) = held {
+                *held = pressed;
+                self.update_dpad_stick();
+                return;
+            }
+        }
+        if pressed {
+            self.held |= 1 << (physical as u32);
+        } else {
+            self.held &= !(1 << (physical as u32));
+        }
+        self.update_pressed();
+    }
+
+    /// Presses every button that something held is mapped to, or that a stick
+    /// is pushing the D-pad towards, and releases the rest.
+    fn update_pressed(&mut self) -> () {
+        let button_map: ButtonMap
+            = self.device_buttons.unwrap_or(self.button_map);
+        let mut pressed: u32 = self.stick_dpad[0] | self.stick_dpad[1];
+        for physical in PhysicalButton::ALL {
+            if self.held & (1 << (physical as u32)) != 0 {
+                pressed |= button_map.get_mask(physical);
+            }
+        }
+        for button in GamepadButton::ALL {
+            let mask: u32 = 1 << (button as u32);
+            if (pressed ^ self.pressed) & mask != 0 {
+                self.update_button(button, pressed & mask != 0);
+            }
         }
+        self.pressed = pressed;
+    }
+
+    /// Moves whichever stick the D-pad drives to wherever the D-pad points.
+    /// Diagonals go just as far as straight directions do.
+    fn update_dpad_stick(&mut self) -> () {
+        let (up, down, left, right): (bool, bool, bool, bool) = self.dpad;
+        let x: f32 = (right as i8 - left as i8) as f32;
+        let y: f32 = (down as i8 - up as i8) as f32;
+        let mut magnitude: f32
+            = AxisMap::to_axis_value(self.axis_map.dpad_magnitude);
+        if x != 0.0 && y != 0.0 {
+            magnitude *= FRAC_1_SQRT_2;
+        }
+        let stick: &mut AnalogStick = match self.axis_map.dpad {
+            DPadMapping::RightStick => &mut self.right_stick,
+            _ => &mut self.left_stick
+        };
+        // Sticks only care whether an axis is X or Y, not which side it's on.
+        stick.update(SdlAxis::LeftX, (x * magnitude) as i16).unwrap();
+        stick.update(SdlAxis::LeftY, (y * magnitude) as i16).unwrap();
     }
 
     /// Updates one of this virtual gamepad's buttons using bitwise operations.
@@ -275,18 +638,266 @@ impl Gamepad {
     /// Mostly derived from Pask's original code, but this also just happens to
     /// be a really nice way of toggling specific button bits.
     fn update_button(&mut self, button: GamepadButton, pressed: bool) -> () {
+        if let Some((mode, _)) = self.turbo_map.get(button) {
+            self.update_turbo(button, mode, pressed);
+            return;
+        }
         if pressed {
             // Toggles button bit on with a bitwise OR.
-            self.buttons |= button.to_bit();
+            self.buttons |= 1 << (button as u32);
         } else {
             // Toggles button bit off with a bitwise AND.
-            self.buttons &= !button.to_bit();
+            self.buttons &= !(1 << (button as u32));
         }
     }
 
     pub fn merge(&mut self, other: Gamepad) -> () {
-        self.buttons |= other.get_buttons();
+        self.buttons |= other.get_pressed_mask();
         self.left_stick.merge(other.get_left_stick());
         self.right_stick.merge(other.get_right_stick());
     }
+
+    /// Merges the other gamepad into this one the way this one's merge policy
+    /// says to.
+    pub fn merge_with_policy(&mut self, other: Gamepad) -> () {
+        match self.merge_policy {
+            MergePolicy::Sum => self.merge(other),
+            MergePolicy::Furthest => {
+                self.buttons |= other.get_pressed_mask();
+                self.left_stick.merge_furthest(other.get_left_stick());
+                self.right_stick.merge_furthest(other.get_right_stick());
+            }
+        }
+    }
+
+    fn update_turbo(
+        &mut self, button: GamepadButton, mode: TurboMode, pressed: bool
+    ) -> () {
+        let mask: u32 = 1 << (button as u32);
+        let was_held: bool = self.turbo_held & mask != 0;
+        if pressed {
+            self.turbo_held |= mask;
+        } else {
+            self.turbo_held &= !mask;
+        }
+        let firing: bool = match mode {
+            TurboMode::Hold => pressed,
+            TurboMode::Toggle if pressed && !was_held
+                => self.turbo_firing & mask == 0,
+            TurboMode::Toggle => return
+        };
+        if firing && self.turbo_firing & mask == 0 {
+            self.turbo_started[button as usize] = self.frame;
+        }
+        if firing {
+            self.turbo_firing |= mask;
+        } else {
+            self.turbo_firing &= !mask;
+        }
+    }
+
+    fn release_buttons(&mut self) -> () {
+        self.buttons = 0;
+        self.held = 0;
+        self.pressed = 0;
+        self.stick_dpad = [0; 2];
+        self.turbo_held = 0;
+        self.turbo_firing = 0;
+    }
+
+    /// Lets go of every button and centers both sticks, along with the D-pad
+    /// directions that might've been pushing one.
+    fn release_all(&mut self) -> () {
+        self.release_buttons();
+        self.dpad = (false, false, false, false);
+        self.left_stick.update(SdlAxis::LeftX, 0).unwrap();
+        self.left_stick.update(SdlAxis::LeftY, 0).unwrap();
+        self.right_stick.update(SdlAxis::RightX, 0).unwrap();
+        self.right_stick.update(SdlAxis::RightY, 0).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl_event_server::SdlButton;
+
+    use super::*;
+
+    fn connected(gamepad_type: GamepadType) -> Gamepad {
+        let mut gamepad: Gamepad = Gamepad::new();
+        gamepad.connect(gamepad_type);
+        return gamepad;
+    }
+
+    fn press(gamepad: &mut Gamepad, button: SdlButton, pressed: bool) -> () {
+        gamepad.update(SdlEvent::ButtonPress {
+            timestamp: 0,
+            which: 0,
+            button: button,
+            pressed: pressed
+        }).unwrap();
+    }
+
+    fn axis(gamepad: &mut Gamepad, axis: SdlAxis, value: i16) -> () {
+        gamepad.update(SdlEvent::AxisMotion {
+            timestamp: 0,
+            which: 0,
+            axis: axis,
+            value: value
+        }).unwrap();
+    }
+
+    #[test]
+    fn changing_type_lets_go_of_a_dpad_that_drives_a_stick() {
+        let mut gamepad: Gamepad = connected(GamepadType::SwitchProController);
+        let mut axis_map: AxisMap = AxisMap::default();
+        axis_map.dpad = DPadMapping::LeftStick;
+        gamepad.set_axis_map(axis_map);
+
+        press(&mut gamepad, SdlButton::DPadUp, true);
+        press(&mut gamepad, SdlButton::A, true);
+        gamepad.set_gamepad_type(GamepadType::SidewaysLeftJoyCon);
+        assert_eq!(gamepad.get_buttons(), 0);
+        assert_eq!(gamepad.get_left_stick().get_position(), (0, 0));
+
+        // Up was let go of along with everything else, so it doesn't come
+        // back with the next direction.
+        press(&mut gamepad, SdlButton::DPadLeft, true);
+        assert_eq!(gamepad.get_left_stick().get_position(), (-i16::MAX, 0));
+    }
+
+    #[test]
+    fn button_maps_press_whatever_they_say() {
+        let mut gamepad: Gamepad = connected(GamepadType::SwitchProController);
+        let mut button_map: ButtonMap = gamepad.get_button_map();
+        button_map.set(
+            PhysicalButton::A, &[GamepadButton::X, GamepadButton::Y]);
+        button_map.set(PhysicalButton::B, &[]);
+        button_map.set(PhysicalButton::Y, &[GamepadButton::X]);
+        button_map.set(PhysicalButton::TriggerLeft, &[GamepadButton::Capture]);
+        gamepad.set_button_map(button_map);
+
+        press(&mut gamepad, SdlButton::A, true);
+        press(&mut gamepad, SdlButton::B, true);
+        press(&mut gamepad, SdlButton::Y, true);
+        axis(&mut gamepad, SdlAxis::TriggerLeft, i16::MAX);
+        assert_eq!(
+            gamepad.get_buttons(),
+            GamepadButton::X.to_bit()
+                | GamepadButton::Y.to_bit()
+                | GamepadButton::Capture.to_bit());
+
+        // X stays pressed for as long as either button mapped to it is held.
+        press(&mut gamepad, SdlButton::A, false);
+        axis(&mut gamepad, SdlAxis::TriggerLeft, 0);
+        assert_eq!(gamepad.get_buttons(), GamepadButton::X.to_bit());
+        press(&mut gamepad, SdlButton::Y, false);
+        assert_eq!(gamepad.get_buttons(), 0);
+    }
+
+    #[test]
+    fn changing_type_brings_its_own_button_map() {
+        let mut gamepad: Gamepad = connected(GamepadType::SwitchProController);
+        gamepad.set_gamepad_type(GamepadType::SidewaysLeftJoyCon);
+        press(&mut gamepad, SdlButton::A, true);
+        axis(&mut gamepad, SdlAxis::TriggerRight, i16::MAX);
+        assert!(gamepad.get_pressed()
+            == vec!(GamepadButton::ZL, GamepadButton::DL));
+    }
+
+    #[test]
+    fn sticks_press_the_dpad_past_their_threshold() {
+        let mut gamepad: Gamepad = connected(GamepadType::SwitchProController);
+        let mut axis_map: AxisMap = AxisMap::default();
+        axis_map.left_stick = StickMapping::DPad;
+        gamepad.set_axis_map(axis_map);
+
+        axis(&mut gamepad, SdlAxis::LeftX, 10000);
+        assert_eq!(gamepad.get_buttons(), 0);
+        axis(&mut gamepad, SdlAxis::LeftX, 20000);
+        axis(&mut gamepad, SdlAxis::LeftY, -20000);
+        assert!(gamepad.get_pressed()
+            == vec!(GamepadButton::DU, GamepadButton::DR));
+        assert_eq!(gamepad.get_left_stick().get_position(), (0, 0));
+
+        axis(&mut gamepad, SdlAxis::LeftX, 0);
+        assert!(gamepad.get_pressed() == vec!(GamepadButton::DU));
+    }
+
+    #[test]
+    fn the_dpad_pushes_a_stick_as_far_as_it_says() {
+        let mut gamepad: Gamepad = connected(GamepadType::SwitchProController);
+        let mut axis_map: AxisMap = AxisMap::default();
+        axis_map.dpad = DPadMapping::RightStick;
+        axis_map.dpad_magnitude = 0.5;
+        gamepad.set_axis_map(axis_map);
+        let magnitude: f32 = AxisMap::to_axis_value(0.5);
+
+        press(&mut gamepad, SdlButton::DPadUp, true);
+        assert_eq!(gamepad.get_buttons(), 0);
+        assert_eq!(
+            gamepad.get_right_stick().get_position(),
+            (0, -magnitude as i16));
+
+        // Diagonals don't go any further than straight up does.
+        press(&mut gamepad, SdlButton::DPadRight, true);
+        let diagonal: i16 = (magnitude * FRAC_1_SQRT_2) as i16;
+        assert_eq!(
+            gamepad.get_right_stick().get_position(), (diagonal, -diagonal));
+    }
+
+    #[test]
+    fn triggers_press_past_their_threshold() {
+        let mut gamepad: Gamepad = connected(GamepadType::SwitchProController);
+        let mut axis_map: AxisMap = AxisMap::default();
+        axis_map.left_trigger_threshold = 0.25;
+        gamepad.set_axis_map(axis_map);
+
+        axis(&mut gamepad, SdlAxis::TriggerLeft, 8000);
+        assert_eq!(gamepad.get_buttons(), 0);
+        axis(&mut gamepad, SdlAxis::TriggerLeft, 9000);
+        assert_eq!(gamepad.get_buttons(), GamepadButton::ZL.to_bit());
+    }
+
+    #[test]
+    fn held_turbo_buttons_fire_every_few_frames() {
+        let mut gamepad: Gamepad = connected(GamepadType::SwitchProController);
+        let mut turbo_map: TurboMap = TurboMap::default();
+        turbo_map.set(GamepadButton::A, Some((TurboMode::Hold, 2)));
+        gamepad.set_turbo_map(turbo_map);
+
+        // SDL's B is the Switch's A.
+        press(&mut gamepad, SdlButton::B, true);
+        let mut fired: Vec<bool> = vec!();
+        for _ in 0..6 {
+            fired.push(gamepad.get_buttons() == GamepadButton::A.to_bit());
+            gamepad.tick();
+        }
+        assert!(fired == vec!(true, true, false, false, true, true));
+
+        press(&mut gamepad, SdlButton::B, false);
+        assert_eq!(gamepad.get_buttons(), 0);
+        assert!(gamepad.get_turbo_firing() == vec!());
+    }
+
+    #[test]
+    fn toggled_turbo_buttons_fire_until_pressed_again() {
+        let mut gamepad: Gamepad = connected(GamepadType::SwitchProController);
+        let mut turbo_map: TurboMap = TurboMap::default();
+        turbo_map.set(GamepadButton::A, Some((TurboMode::Toggle, 1)));
+        gamepad.set_turbo_map(turbo_map);
+
+        press(&mut gamepad, SdlButton::B, true);
+        press(&mut gamepad, SdlButton::B, false);
+        assert!(gamepad.get_turbo_firing() == vec!(GamepadButton::A));
+        assert_eq!(gamepad.get_buttons(), GamepadButton::A.to_bit());
+        gamepad.tick();
+        assert_eq!(gamepad.get_buttons(), 0);
+        gamepad.tick();
+        assert_eq!(gamepad.get_buttons(), GamepadButton::A.to_bit());
+
+        press(&mut gamepad, SdlButton::B, true);
+        assert!(gamepad.get_turbo_firing() == vec!());
+        assert_eq!(gamepad.get_buttons(), 0);
+    }
 }
@@ -0,0 +1,79 @@
+use sdl_event_server::{SdlAxis, SdlButton, SdlEvent};
+use serde::{Serialize, Deserialize};
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+/// Which part of a physical controller gets to drive a gamepad.
+///
+/// Splitting a controller uses the halves, while merging can use any of these,
+/// so that one player can steer with the left stick while another presses the
+/// buttons, for example.
+pub enum InputFilter {
+    All,
+    // The left stick, D-pad, left shoulder and trigger, and minus.
+    LeftHalf,
+    // Anything that isn't on the left half, like the face buttons.
+    RightHalf,
+    // Moving the sticks, but not clicking them.
+    LeftStick,
+    RightStick,
+    Sticks,
+    // Anything that isn't moving a stick, triggers included.
+    Buttons
+}
+
+impl Default for InputFilter {
+    fn default() -> InputFilter {
+        return InputFilter::All;
+    }
+}
+
+/// Whether an axis or button is on the left half of a controller.
+fn is_left(event: SdlEvent) -> Option<bool> {
+    match event {
+        SdlEvent::AxisMotion { timestamp: _, which: _, axis, value: _ }
+            => Some(match axis {
+                SdlAxis::LeftX | SdlAxis::LeftY | SdlAxis::TriggerLeft
+                    => true,
+                _ => false
+            }),
+        SdlEvent::ButtonPress { timestamp: _, which: _, button, pressed: _ }
+            => Some(match button {
+                SdlButton::Back | SdlButton::LeftStick
+                | SdlButton::LeftShoulder | SdlButton::DPadUp
+                | SdlButton::DPadDown | SdlButton::DPadLeft
+                | SdlButton::DPadRight | SdlButton::Misc1
+                | SdlButton::Paddle2 | SdlButton::Paddle4 => true,
+                _ => false
+            }),
+        _ => None
+    }
+}
+
+/// Whether an event moves a stick, rather than pressing a button or trigger.
+fn is_stick(event: SdlEvent) -> bool {
+    match event {
+        SdlEvent::AxisMotion { timestamp: _, which: _, axis, value: _ }
+            => !axis.is_trigger(),
+        _ => false
+    }
+}
+
+impl InputFilter {
+    /// Whether the event should go through. Controllers being added and
+    /// removed always do.
+    pub fn allows(&self, event: SdlEvent) -> bool {
+        let left: bool = match is_left(event) {
+            None => return true,
+            Some(left) => left
+        };
+        match self {
+            InputFilter::All => true,
+            InputFilter::LeftHalf => left,
+            InputFilter::RightHalf => !left,
+            InputFilter::LeftStick => is_stick(event) && left,
+            InputFilter::RightStick => is_stick(event) && !left,
+            InputFilter::Sticks => is_stick(event),
+            InputFilter::Buttons => !is_stick(event)
+        }
+    }
+}
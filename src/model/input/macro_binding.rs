@@ -0,0 +1,48 @@
+use crate::model::input::{
+    button_map::PhysicalButton,
+    script_event::ScriptEvent
+};
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Serialize, Deserialize)]
+/// A script that runs on a gamepad whenever a physical button, or every button
+/// in a chord, is pressed on it.
+pub struct MacroBinding {
+    pub trigger: Vec<PhysicalButton>,
+    #[serde(default)]
+    pub script: Vec<ScriptEvent>,
+    // A script in the library to run instead. It's looked up each time the
+    // macro is set off, and copied into the script above, which runs in its
+    // place if it's been deleted since.
+    #[serde(default)]
+    pub script_name: Option<String>,
+    // Pressing the trigger again while the script's running stops it, instead
+    // of doing nothing.
+    #[serde(default)]
+    pub interrupt: bool,
+    // Runs the script again each time it finishes, for as long as the trigger
+    // is held.
+    #[serde(default)]
+    pub loop_while_held: bool,
+    // Ignores the gamepad's controllers while the script's running, so only
+    // the script drives it. They catch up with whatever changed once it's
+    // done.
+    #[serde(default)]
+    pub block_input: bool
+}
+
+/// Packs a trigger into a mask of physical buttons, by their order in
+/// PhysicalButton::ALL.
+pub fn to_trigger_mask(trigger: &[PhysicalButton]) -> u32 {
+    let mut mask: u32 = 0;
+    for physical in trigger {
+        mask |= 1 << (*physical as u32);
+    }
+    return mask;
+}
+
+impl MacroBinding {
+    pub fn get_trigger_mask(&self) -> u32 {
+        return to_trigger_mask(&self.trigger);
+    }
+}
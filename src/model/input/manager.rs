@@ -1,30 +1,157 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
-use crate::model::input::{
-    gamepad::{GamepadType, Gamepad},
-    script_event::ScriptEvent
+use crate::model::{
+    application_event::ApplicationEvent,
+    input::{
+        axis_map::AxisMap,
+        button_map::{ButtonMap, PhysicalButton},
+        device_info::DeviceInfo,
+        gamepad::{
+            GamepadButton, GamepadType, Gamepad, GamepadSettings, MergePolicy
+        },
+        input_filter::InputFilter,
+        macro_binding::{MacroBinding, to_trigger_mask},
+        turbo::TurboMap,
+        script_event::ScriptEvent,
+        script_status::ScriptStatus,
+        snapshot::GamepadSnapshot
+    },
+    script_store::ScriptStore
 };
-use sdl_event_server::{SdlButton, SdlEvent};
+use sdl_event_server::{SdlAxis, SdlButton, SdlEvent};
 
 pub const NUM_GAMEPADS: usize = 8;
 const CONNECT_BUTTON: SdlButton = SdlButton::RightShoulder;
 
+/// Returns the "which" of the controller that an event came from.
+fn get_event_which(event: SdlEvent) -> usize {
+    let which: u32 = match event {
+        SdlEvent::ControllerAdded { timestamp: _, which }
+            => which,
+        SdlEvent::ControllerRemoved { timestamp: _, which }
+            => which,
+        SdlEvent::AxisMotion { timestamp: _, which, axis: _, value: _ }
+            => which,
+        SdlEvent::ButtonPress { timestamp: _, which, button: _, pressed: _ }
+            => which
+    };
+    return which as usize;
+}
+
+/// Whether two events are for the same button or axis on the same controller,
+/// so that the newer one makes the older one moot.
+fn is_same_input(a: SdlEvent, b: SdlEvent) -> bool {
+    match (a, b) {
+        (
+            SdlEvent::AxisMotion { timestamp: _, which, axis, value: _ },
+            SdlEvent::AxisMotion {
+                timestamp: _, which: other_which, axis: other_axis, value: _
+            }
+        ) => which == other_which && axis as usize == other_axis as usize,
+        (
+            SdlEvent::ButtonPress { timestamp: _, which, button, pressed: _ },
+            SdlEvent::ButtonPress {
+                timestamp: _,
+                which: other_which,
+                button: other_button,
+                pressed: _
+            }
+        ) => which == other_which && button == other_button,
+        _ => false
+    }
+}
+
+#[derive(Copy, Clone)]
+/// A controller that drives a gamepad without being in the indices, either
+/// because it was merged into a gamepad that another controller is driving, or
+/// because it was split across more than one.
+///
+/// It keeps a gamepad of its own, with the same type and settings as the one
+/// it's bound to, which gets merged in whenever the gamepads are read.
+struct Binding {
+    which: usize,
+    i: usize,
+    gamepad: Gamepad,
+    filter: InputFilter
+}
+
+#[derive(Clone)]
+/// A script whose events are still in the buffer, or that's still waiting.
+struct RunningScript {
+    id: usize,
+    i: usize,
+    // How many more updates until its last event goes through, out of how many
+    // there were to begin with.
+    remaining: usize,
+    length: usize,
+    // Everything the script presses or moves, which gets let go of if it's
+    // cancelled partway through.
+    buttons: Vec<SdlButton>,
+    axes: Vec<SdlAxis>
+}
+
+/// A macro on a gamepad, along with the script it's running, if any.
+struct Macro {
+    i: usize,
+    binding: MacroBinding,
+    running: Option<usize>
+}
+
 pub struct GamepadManager {
     anarchy_mode: bool,
     gamepads: [Gamepad; NUM_GAMEPADS],
     indices: HashMap<usize, usize>,
+    // What each gamepad lets through from the controller in the indices.
+    filters: [InputFilter; NUM_GAMEPADS],
+    // Controllers that were merged into another's gamepad, or split across
+    // more than one, so they aren't (only) in the indices.
+    bindings: Vec<Binding>,
+    // The physical device behind each "which", if its source told us.
+    devices: HashMap<usize, DeviceInfo>,
+    // Settings for each device we've seen, by DeviceInfo.get_key(). These
+    // outlive the device being connected, unlike the ones on its gamepad.
+    device_settings: HashMap<String, GamepadSettings>,
 
-    buffer: Vec<(SdlEvent, usize)>
+    // Events from scripts go straight to their gamepad, rather than to
+    // whatever's driving it, so they come with the script's ID.
+    buffer: Vec<(SdlEvent, Option<usize>, usize)>,
+    scripts: Vec<RunningScript>,
+    next_script_id: usize,
+    macros: Vec<Macro>,
+    // Where macros look up their named scripts whenever they're set off.
+    library: ScriptStore,
+    // The physical buttons held on each gamepad, as masks by their order in
+    // PhysicalButton::ALL, which is what sets off macros.
+    held: [u32; NUM_GAMEPADS],
+    // The latest event for every input that a macro blocked, which all go
+    // through once nothing's blocking them, so nothing stays stuck where it
+    // was when the macro started.
+    blocked: Vec<SdlEvent>,
+    events: Vec<ApplicationEvent>,
+    // How long a single update takes, for scripts that wait in milliseconds.
+    frame: Duration
 }
 
 impl GamepadManager {
-    pub fn new() -> GamepadManager {
+    pub fn new(frame: Duration, library: ScriptStore) -> GamepadManager {
         return GamepadManager {
             anarchy_mode: false,
             gamepads: [Gamepad::new(); NUM_GAMEPADS],
             indices: HashMap::new(),
+            filters: [InputFilter::All; NUM_GAMEPADS],
+            bindings: vec!(),
+            devices: HashMap::new(),
+            device_settings: HashMap::new(),
 
-            buffer: vec!()
+            buffer: vec!(),
+            scripts: vec!(),
+            next_script_id: 0,
+            macros: vec!(),
+            library: library,
+            held: [0; NUM_GAMEPADS],
+            blocked: vec!(),
+            events: vec!(),
+            frame: frame
         }
     }
 
@@ -36,8 +163,22 @@ impl GamepadManager {
         self.anarchy_mode = anarchy_mode;
     }
 
+    /// Returns every gamepad, with whatever was merged into each of them.
     pub fn get_gamepads(&self) -> [Gamepad; NUM_GAMEPADS] {
-        return self.gamepads.clone();
+        let mut gamepads: [Gamepad; NUM_GAMEPADS] = self.gamepads.clone();
+        for binding in self.bindings.iter() {
+            gamepads[binding.i].merge_with_policy(binding.gamepad);
+        }
+        return gamepads;
+    }
+
+    pub fn get_snapshots(&self) -> Vec<GamepadSnapshot> {
+        let mut snapshots: Vec<GamepadSnapshot> = vec!();
+        for (i, gamepad) in self.get_gamepads().iter().enumerate() {
+            snapshots.push(GamepadSnapshot::new(
+                i, gamepad, self.get_device(i)));
+        }
+        return snapshots;
     }
 
     pub fn get_delay(&self, i: usize) -> usize {
@@ -46,6 +187,7 @@ impl GamepadManager {
 
     pub fn set_delay(&mut self, i: usize, delay: usize) -> () {
         self.gamepads[i].set_delay(delay);
+        self.remember_settings(i);
     }
 
     pub fn get_left_deadzone(&self, i: usize) -> f32 {
@@ -54,6 +196,7 @@ impl GamepadManager {
 
     pub fn set_left_deadzone(&mut self, i: usize, deadzone: f32) -> () {
         self.gamepads[i].set_left_deadzone(deadzone);
+        self.remember_settings(i);
     }
 
     pub fn get_right_deadzone(&self, i: usize) -> f32 {
@@ -62,53 +205,420 @@ impl GamepadManager {
 
     pub fn set_right_deadzone(&mut self, i: usize, deadzone: f32) -> () {
         self.gamepads[i].set_right_deadzone(deadzone);
+        self.remember_settings(i);
     }
 
-    pub fn run_script(
-        &mut self, i: usize, script: Vec<ScriptEvent>
+    pub fn get_button_map(&self, i: usize) -> ButtonMap {
+        return self.gamepads[i].get_button_map();
+    }
+
+    pub fn set_button_map(&mut self, i: usize, button_map: ButtonMap) -> () {
+        self.gamepads[i].set_button_map(button_map);
+        self.remember_settings(i);
+    }
+
+    pub fn get_axis_map(&self, i: usize) -> AxisMap {
+        return self.gamepads[i].get_axis_map();
+    }
+
+    pub fn set_axis_map(&mut self, i: usize, axis_map: AxisMap) -> () {
+        self.gamepads[i].set_axis_map(axis_map);
+        self.remember_settings(i);
+    }
+
+    /// Changes what a connected gamepad is emulating. Returns false if it
+    /// isn't connected.
+    pub fn set_gamepad_type(
+        &mut self, i: usize, gamepad_type: GamepadType
+    ) -> bool {
+        if let GamepadType::Disconnected = self.gamepads[i].get_gamepad_type() {
+            return false;
+        }
+        self.gamepads[i].set_gamepad_type(gamepad_type);
+        self.remember_settings(i);
+        return true;
+    }
+
+    pub fn get_turbo_map(&self, i: usize) -> TurboMap {
+        return self.gamepads[i].get_turbo_map();
+    }
+
+    pub fn set_turbo_map(&mut self, i: usize, turbo_map: TurboMap) -> () {
+        self.gamepads[i].set_turbo_map(turbo_map);
+        self.remember_settings(i);
+    }
+
+    pub fn get_merge_policy(&self, i: usize) -> MergePolicy {
+        return self.gamepads[i].get_merge_policy();
+    }
+
+    pub fn set_merge_policy(
+        &mut self, i: usize, merge_policy: MergePolicy
     ) -> () {
-        // While this is initialized to 0, we know it'll have some other value
-        // set to it as a result of the loop.
-        let mut which: u32 = 0;
-        for key in self.indices.keys() {
-            if let Some(j) = self.indices.get(key) {
-                if j == &i {
-                    which = *key as u32;
-                }
+        self.gamepads[i].set_merge_policy(merge_policy);
+        self.remember_settings(i);
+    }
+
+    pub fn is_connected(&self, i: usize) -> bool {
+        return *self.gamepads[i].get_gamepad_type()
+            != GamepadType::Disconnected;
+    }
+
+    /// Returns every gamepad that a controller drives, whether it's in the
+    /// indices or bound.
+    fn get_slots(&self, which: usize) -> Vec<usize> {
+        return self.indices
+            .get(&which)
+            .cloned()
+            .into_iter()
+            .chain(self.bindings
+                .iter()
+                .filter(|binding| binding.which == which)
+                .map(|binding| binding.i))
+            .collect();
+    }
+
+    /// Whether a gamepad has half of a split controller driving it, which is
+    /// one that drives more than one gamepad at once.
+    fn is_split(&self, i: usize) -> bool {
+        return self.get_which(i)
+            .into_iter()
+            .chain(self.bindings
+                .iter()
+                .filter(|binding| binding.i == i)
+                .map(|binding| binding.which))
+            .any(|which| self.get_slots(which).len() > 1);
+    }
+
+    /// Narrows down what every controller driving a gamepad gets to drive.
+    /// Letting everything through leaves them as they were.
+    fn filter_slot(&mut self, i: usize, filter: InputFilter) -> () {
+        if filter == InputFilter::All {
+            return;
+        }
+        if self.get_which(i).is_some() {
+            self.filters[i] = filter;
+        }
+        for binding in self.bindings.iter_mut() {
+            if binding.i == i {
+                binding.filter = filter;
             }
         }
+    }
+
+    /// Merges every controller driving gamepad j into gamepad i, which frees
+    /// up j. Returns false if either of them isn't connected, or is half of a
+    /// split controller, which has to be unsplit first.
+    ///
+    /// Each filter replaces what the controllers from that gamepad get to
+    /// drive, unless it lets everything through. Gamepad j's macros move to i,
+    /// except for any whose trigger i already uses, but whatever j was running
+    /// is cancelled.
+    pub fn merge(
+        &mut self, i: usize, j: usize, i_filter: InputFilter,
+        j_filter: InputFilter
+    ) -> bool {
+        if i == j || !self.is_connected(i) || !self.is_connected(j)
+            || self.is_split(i) || self.is_split(j) {
+            return false;
+        }
+        let running: Vec<usize> = self.scripts
+            .iter()
+            .filter(|script| script.i == j)
+            .map(|script| script.id)
+            .collect();
+        for id in running {
+            self.cancel_script(id);
+        }
+        let triggers: Vec<u32> = self.macros
+            .iter()
+            .filter(|bound| bound.i == i)
+            .map(|bound| bound.binding.get_trigger_mask())
+            .collect();
+        self.macros.retain(|bound| bound.i != j
+            || !triggers.contains(&bound.binding.get_trigger_mask()));
+        for bound in self.macros.iter_mut() {
+            if bound.i == j {
+                bound.i = i;
+            }
+        }
+        self.held[i] |= self.held[j];
+        self.held[j] = 0;
+
+        self.filter_slot(i, i_filter);
+        self.filter_slot(j, j_filter);
+        if let Some(which) = self.get_which(j) {
+            self.indices.remove(&which);
+            // Whatever's held down stays held, unless the maps change.
+            let mut gamepad: Gamepad = self.gamepads[j];
+            gamepad.connect(*self.gamepads[i].get_gamepad_type());
+            gamepad.set_settings(self.gamepads[i].get_settings());
+            self.bindings.push(Binding {
+                which: which,
+                i: i,
+                gamepad: gamepad,
+                filter: self.filters[j]
+            });
+            self.filters[j] = InputFilter::All;
+        }
+        for binding in self.bindings.iter_mut() {
+            if binding.i == j {
+                binding.i = i;
+            }
+        }
+        self.gamepads[j].disconnect();
+        self.events.push(ApplicationEvent::ControllerRemoved { i: j });
+        return true;
+    }
+
+    /// Gives every controller that was merged into gamepad i a gamepad of its
+    /// own again, and lets whatever stays drive all of i again. Any that don't
+    /// fit will have to connect again.
+    pub fn unmerge(&mut self, i: usize) -> () {
+        // Half of a split controller is still in the indices, and stays put.
+        let (unmerged, kept): (Vec<Binding>, Vec<Binding>) = self.bindings
+            .drain(..)
+            .partition(|binding| binding.i == i
+                && !self.indices.contains_key(&binding.which));
+        self.bindings = kept;
+        if !self.is_split(i) {
+            self.filters[i] = InputFilter::All;
+        }
+        for binding in unmerged {
+            self.connect(binding.which as u32);
+        }
+    }
 
-        let mut delay: usize = 0;
-        for event in script {
+    /// Splits the controller driving gamepad i across gamepads i and j, with
+    /// its left half as a sideways left Joy-Con on i and its right half as a
+    /// sideways right Joy-Con on j. Returns false if i has no controller of its
+    /// own to split, or j is already in use.
+    pub fn split(&mut self, i: usize, j: usize) -> bool {
+        if i == j || self.is_connected(j) || self.filters[i] != InputFilter::All
+        {
+            return false;
+        }
+        let which: usize = match self.get_which(i) {
+            Some(which) => which,
+            None => return false
+        };
+
+        // Each half only has the one shoulder and trigger, so the trigger
+        // becomes whichever of SL and SR the shoulder isn't.
+        self.filters[i] = InputFilter::LeftHalf;
+        self.gamepads[i].set_gamepad_type(GamepadType::SidewaysLeftJoyCon);
+        let mut left_map: ButtonMap = self.gamepads[i].get_button_map();
+        left_map.set(PhysicalButton::TriggerLeft, &[GamepadButton::SRL]);
+        self.gamepads[i].set_button_map(left_map);
+
+        self.gamepads[j].set_gamepad_type(GamepadType::SidewaysRightJoyCon);
+        let mut right_map: ButtonMap = self.gamepads[j].get_button_map();
+        right_map.set(PhysicalButton::TriggerRight, &[GamepadButton::SLR]);
+        self.gamepads[j].set_button_map(right_map);
+        let mut right_half: Gamepad = Gamepad::new();
+        right_half.set_device_buttons(self.get_device_buttons(which));
+        self.bindings.push(Binding {
+            which: which,
+            i: j,
+            gamepad: right_half,
+            filter: InputFilter::RightHalf
+        });
+        self.events.push(ApplicationEvent::ControllerAdded { i: j });
+        return true;
+    }
+
+    /// Gives all of the controller driving gamepad i back to it as a Pro
+    /// Controller, which frees up wherever else it was split to. Returns false
+    /// if it wasn't split.
+    pub fn unsplit(&mut self, i: usize) -> bool {
+        let which: usize = match self.get_which(i) {
+            Some(which) if self.get_slots(which).len() > 1 => which,
+            _ => return false
+        };
+        self.filters[i] = InputFilter::All;
+        self.gamepads[i].set_gamepad_type(GamepadType::SwitchProController);
+        self.remove_bindings(which);
+        return true;
+    }
+
+    pub fn get_settings(&self, i: usize) -> GamepadSettings {
+        return self.gamepads[i].get_settings();
+    }
+
+    pub fn set_settings(&mut self, i: usize, settings: GamepadSettings) -> () {
+        self.gamepads[i].set_settings(settings);
+        self.remember_settings(i);
+    }
+
+    /// Records what the device behind "which" is, so that it can get its
+    /// settings back whenever it's connected.
+    pub fn identify(&mut self, which: u32, device: DeviceInfo) -> () {
+        self.devices.insert(which as usize, device);
+    }
+
+    pub fn get_device(&self, i: usize) -> Option<DeviceInfo> {
+        return self.get_which(i)
+            .and_then(|which| self.devices.get(&which))
+            .cloned();
+    }
+
+    /// Returns what the device behind "which" says its buttons press, if it
+    /// decides that for itself.
+    fn get_device_buttons(&self, which: usize) -> Option<ButtonMap> {
+        return self.devices.get(&which).and_then(|device| device.buttons);
+    }
+
+    pub fn get_device_settings(&self) -> HashMap<String, GamepadSettings> {
+        return self.device_settings.clone();
+    }
+
+    pub fn set_device_settings(
+        &mut self, device_settings: HashMap<String, GamepadSettings>
+    ) -> () {
+        self.device_settings.extend(device_settings);
+    }
+
+    /// Puts a script's events in the buffer for a gamepad, returning the ID
+    /// that they go by, which doubles as its handle. Fails if the script is
+    /// too big to expand.
+    pub fn run_script(
+        &mut self, i: usize, script: &[ScriptEvent]
+    ) -> Result<usize, String> {
+        let (expanded, length): (Vec<(ScriptEvent, usize)>, usize)
+            = ScriptEvent::expand(script, self.frame)?;
+        // The "which" doesn't matter, since these go straight to the gamepad.
+        let which: u32 = self.get_which(i).unwrap_or(0) as u32;
+        let id: usize = self.next_script_id;
+        self.next_script_id += 1;
+
+        let mut buttons: Vec<SdlButton> = vec!();
+        let mut axes: Vec<SdlAxis> = vec!();
+        for (event, delay) in expanded {
             match event {
-                ScriptEvent::AxisMotion { axis, value } 
-                    => self.buffer.insert(
-                        0,
-                        (
-                            SdlEvent::AxisMotion {
-                                timestamp: 0,
-                                which: which,
-                                axis: axis,
-                                value: value
-                            },
-                            delay
-                        )),
-                ScriptEvent::ButtonPress { button, pressed }
-                    => self.buffer.insert(
-                        0,
-                        (
-                            SdlEvent::ButtonPress {
-                                timestamp: 0,
-                                which: which,
-                                button: button,
-                                pressed: pressed
-                            },
-                            delay
-                        )),
-                // Waiting will delay all the events after it.
-                ScriptEvent::Wait { frames } => delay += frames
+                ScriptEvent::ButtonPress { button, pressed: _ }
+                    if !buttons.contains(&button) => buttons.push(button),
+                ScriptEvent::AxisMotion { axis, value: _ }
+                    if !axes.iter().any(|a| *a as usize == axis as usize)
+                    => axes.push(axis),
+                _ => ()
+            }
+            if let Some(event) = event.to_sdl(which) {
+                self.buffer.insert(0, (event, Some(id), delay));
+            }
+        }
+        self.scripts.push(RunningScript {
+            id: id,
+            i: i,
+            remaining: length,
+            length: length,
+            buttons: buttons,
+            axes: axes
+        });
+        return Ok(id);
+    }
+
+    /// Returns how far along every running script is, oldest first.
+    pub fn get_running_scripts(&self) -> Vec<ScriptStatus> {
+        return self.scripts
+            .iter()
+            .map(|script| ScriptStatus {
+                handle: script.id,
+                i: script.i,
+                frames_elapsed: script.length - script.remaining,
+                frames_remaining: script.remaining
+            })
+            .collect();
+    }
+
+    /// Stops a script, taking whatever it hasn't done yet out of the buffer,
+    /// then lets go of every button and stick it touched. Returns false if it
+    /// already finished, or never existed.
+    pub fn cancel_script(&mut self, id: usize) -> bool {
+        let maybe_k: Option<usize> = self.scripts
+            .iter()
+            .position(|script| script.id == id);
+        let script: RunningScript = match maybe_k {
+            Some(k) => self.scripts.remove(k),
+            None => return false
+        };
+        self.buffer.retain(|(_, maybe_id, _)| *maybe_id != Some(id));
+        for bound in self.macros.iter_mut() {
+            if bound.running == Some(id) {
+                bound.running = None;
+            }
+        }
+
+        if self.is_connected(script.i) {
+            let which: u32 = self.get_which(script.i).unwrap_or(0) as u32;
+            let releases: Vec<ScriptEvent> = script.buttons
+                .iter()
+                .map(|button| ScriptEvent::ButtonPress {
+                    button: *button,
+                    pressed: false
+                })
+                .chain(script.axes.iter().map(|axis| ScriptEvent::AxisMotion {
+                    axis: *axis,
+                    value: 0
+                }))
+                .collect();
+            for release in releases {
+                if let Some(event) = release.to_sdl(which) {
+                    if let Err(e) = self.gamepads[script.i].update(event) {
+                        eprintln!("{}", e);
+                    }
+                }
             }
         }
+        self.events.push(ApplicationEvent::ScriptCancelled {
+            i: script.i,
+            handle: id
+        });
+        self.unblock();
+        return true;
+    }
+
+    /// Returns every macro on a gamepad.
+    pub fn get_macros(&self, i: usize) -> Vec<MacroBinding> {
+        return self.macros
+            .iter()
+            .filter(|bound| bound.i == i)
+            .map(|bound| bound.binding.clone())
+            .collect();
+    }
+
+    /// Binds a macro to a gamepad, replacing whichever one had the same
+    /// trigger. Macros stay on their gamepad, even if its controller changes.
+    /// Fails if the script is too big to expand.
+    pub fn set_macro(
+        &mut self, i: usize, binding: MacroBinding
+    ) -> Result<(), String> {
+        ScriptEvent::expand(&binding.script, self.frame)?;
+        self.clear_macro(i, &binding.trigger.clone());
+        self.macros.push(Macro {
+            i: i,
+            binding: binding,
+            running: None
+        });
+        return Ok(());
+    }
+
+    /// Removes the macro with the given trigger from a gamepad, stopping it if
+    /// it's running. Returns false if there wasn't one.
+    pub fn clear_macro(
+        &mut self, i: usize, trigger: &[PhysicalButton]
+    ) -> bool {
+        let trigger_mask: u32 = to_trigger_mask(trigger);
+        let maybe_k: Option<usize> = self.macros
+            .iter()
+            .position(|bound| bound.i == i
+                && bound.binding.get_trigger_mask() == trigger_mask);
+        if let Some(k) = maybe_k {
+            if let Some(id) = self.macros.remove(k).running {
+                self.cancel_script(id);
+            }
+            return true;
+        }
+        return false;
     }
 
     pub fn swap(&mut self, i: usize, j: usize) -> () {
@@ -116,27 +626,69 @@ impl GamepadManager {
         self.gamepads[i] = self.gamepads[j];
         self.gamepads[j] = temp;
 
-        // Remember to also change self.indices ("which" -> indices).
-        let mut temp_i: usize = 0;
-        let mut temp_j: usize = 0;
-        for key in self.indices.keys() {
-            if self.indices.get(key).unwrap() == &i {
-                temp_i = *key;
-            } else if self.indices.get(key).unwrap() == &j {
-                temp_j = *key;
+        let temp_held: u32 = self.held[i];
+        self.held[i] = self.held[j];
+        self.held[j] = temp_held;
+        for bound in self.macros.iter_mut() {
+            if bound.i == i {
+                bound.i = j;
+            } else if bound.i == j {
+                bound.i = i;
+            }
+        }
+        for script in self.scripts.iter_mut() {
+            if script.i == i {
+                script.i = j;
+            } else if script.i == j {
+                script.i = i;
+            }
+        }
+
+        let temp_filter: InputFilter = self.filters[i];
+        self.filters[i] = self.filters[j];
+        self.filters[j] = temp_filter;
+
+        // Remember to also change self.indices ("which" -> indices). Either
+        // gamepad might only be driven by bindings, and not be in there.
+        let maybe_which_i: Option<usize> = self.get_which(i);
+        let maybe_which_j: Option<usize> = self.get_which(j);
+        if let Some(which) = maybe_which_i {
+            self.indices.insert(which, j);
+        }
+        if let Some(which) = maybe_which_j {
+            self.indices.insert(which, i);
+        }
+        for binding in self.bindings.iter_mut() {
+            if binding.i == i {
+                binding.i = j;
+            } else if binding.i == j {
+                binding.i = i;
             }
         }
-        self.indices.insert(temp_i, j);
-        self.indices.insert(temp_j, i);
+        self.events.push(ApplicationEvent::ControllersSwapped { i: i, j: j });
     }
 
     pub fn update(&mut self, events: &mut Vec<SdlEvent>) -> () {
         self.read_events(events);
+        let started: usize = self.next_script_id;
         self.read_buffer();
+        self.update_scripts(started);
+        for gamepad in self.gamepads.iter_mut() {
+            gamepad.tick();
+        }
+        for binding in self.bindings.iter_mut() {
+            binding.gamepad.tick();
+        }
+    }
+
+    /// Returns the events that happened since the last time this was called.
+    pub fn take_events(&mut self) -> Vec<ApplicationEvent> {
+        return self.events.drain(..).collect();
     }
 
     fn read_events(&mut self, events: &mut Vec<SdlEvent>) -> () {
-        while let Some(event) = events.pop() {
+        // The buffer is newest-first, so go through these oldest-first.
+        for event in events.drain(..) {
             let i: u32;
             match event {
                 SdlEvent::ControllerAdded {
@@ -166,75 +718,716 @@ impl GamepadManager {
                     pressed: _
                 } => i = which
             }
-            if let Some(j) = self.indices.get(&(i as usize)) {
+            // Merged controllers go with the delay of the gamepad they were
+            // merged into.
+            let maybe_j: Option<usize> = self.indices
+                .get(&(i as usize))
+                .cloned()
+                .or(self.bindings
+                    .iter()
+                    .find(|binding| binding.which == i as usize)
+                    .map(|binding| binding.i));
+            if let Some(j) = maybe_j {
                 self.buffer.insert(
                     0,
-                    (event, self.gamepads[*j].get_delay())
+                    (event, None, self.gamepads[j].get_delay())
                 );
             } else {
-                self.buffer.insert(0, (event, 0));
+                self.buffer.insert(0, (event, None, 0));
             }
         }
     }
 
     fn read_buffer(&mut self) -> () {
-        // We're only going to loop the original buffer length.
-        let mut i = 0;
-        let mut buffer_len: usize = self.buffer.len();
-
-        while i < buffer_len {
-            if let Some((event, delay)) = self.buffer.pop() {
-                if delay == 0 {
-                    if let Some(i) = self.get_indices(event) {
-                        if let Err(e) = self.gamepads[*i].update(event) {
-                            eprintln!("{}", e);
-                        }
-                    } else {
-                        if let SdlEvent::ButtonPress {
-                            timestamp: _,
-                            which,
-                            button: CONNECT_BUTTON,
-                            pressed: true
-                        } = event {
-                            self.connect(which);
-                        }
+        // We're only going to loop over what was in the buffer to begin with,
+        // and events that are still delayed get put back in afterwards.
+        let mut buffer: Vec<(SdlEvent, Option<usize>, usize)>
+            = std::mem::take(&mut self.buffer);
+        let mut delayed: Vec<(SdlEvent, Option<usize>, usize)> = vec!();
+
+        // Popping goes from oldest to newest.
+        while let Some((event, maybe_id, delay)) = buffer.pop() {
+            if delay != 0 {
+                delayed.insert(0, (event, maybe_id, delay - 1));
+            } else if let Some(id) = maybe_id {
+                // Scripts that were cancelled, or are on an empty slot, don't
+                // do anything.
+                let maybe_i: Option<usize> = self.scripts
+                    .iter()
+                    .find(|script| script.id == id)
+                    .map(|script| script.i);
+                if let Some(i) = maybe_i.filter(|i| self.is_connected(*i)) {
+                    if let Err(e) = self.gamepads[i].update(event) {
+                        eprintln!("{}", e);
+                    }
+                }
+            } else {
+                let bound: bool = self.update_bindings(event);
+                if let Some(i) = self.get_indices(event).cloned() {
+                    if !self.filters[i].allows(event) {
+                        continue;
+                    }
+                    self.update_macros(i, event);
+                    if self.is_blocked(i) {
+                        self.block(event);
+                        continue;
+                    }
+                    if let Err(e) = self.gamepads[i].update(event) {
+                        eprintln!("{}", e);
+                    }
+                } else if !bound {
+                    if let SdlEvent::ButtonPress {
+                        timestamp: _,
+                        which,
+                        button: CONNECT_BUTTON,
+                        pressed: true
+                    } = event {
+                        self.connect(which);
                     }
-                } else {
-                    self.buffer.insert(0, (event, delay - 1));
-                    i += 1;
-                    buffer_len += 1;
                 }
             }
         }
+        // Macros might have started scripts while we were at it, which are
+        // newer than anything that was delayed.
+        self.buffer.extend(delayed);
+    }
+
+    /// Counts down every script that was started before the given ID. Ones
+    /// that macros started while reading the buffer haven't had their events
+    /// wait a frame yet, so they don't either.
+    fn update_scripts(&mut self, started: usize) -> () {
+        let mut running: Vec<RunningScript> = vec!();
+        let mut finished: Vec<usize> = vec!();
+        for script in self.scripts.drain(..) {
+            if script.id >= started {
+                running.push(script);
+            } else if script.remaining == 0 {
+                self.events.push(ApplicationEvent::ScriptFinished {
+                    i: script.i,
+                    handle: script.id
+                });
+                finished.push(script.id);
+            } else {
+                running.push(RunningScript {
+                    remaining: script.remaining - 1,
+                    ..script
+                });
+            }
+        }
+        self.scripts = running;
+
+        // Macros that loop start over if their trigger's still held.
+        for k in 0..self.macros.len() {
+            let bound: &mut Macro = &mut self.macros[k];
+            if !bound.running.map_or(false, |id| finished.contains(&id)) {
+                continue;
+            }
+            bound.running = None;
+            let mask: u32 = bound.binding.get_trigger_mask();
+            if bound.binding.loop_while_held
+                && self.held[bound.i] & mask == mask {
+                self.start_macro(k);
+            }
+        }
+        self.unblock();
+    }
+
+    /// Runs a macro's script. Named scripts are looked up again every time,
+    /// so editing one changes every macro that runs it. If it's since been
+    /// deleted or can't run anymore, the last copy that could runs instead.
+    fn start_macro(&mut self, k: usize) -> () {
+        let i: usize = self.macros[k].i;
+        let maybe_named: Option<Vec<ScriptEvent>> = self.macros[k]
+            .binding
+            .script_name
+            .as_ref()
+            .and_then(|name| self.library.load(name).ok());
+        if let Some(script) = maybe_named {
+            if let Ok(id) = self.run_script(i, &script) {
+                self.macros[k].binding.script = script;
+                self.macros[k].running = Some(id);
+                return;
+            }
+        }
+        let script: Vec<ScriptEvent> = self.macros[k].binding.script.clone();
+        // This was already expanded once when the macro was set.
+        self.macros[k].running = self.run_script(i, &script).ok();
+    }
+
+    /// Keeps track of the physical buttons held on a gamepad, then starts or
+    /// stops whatever macros that sets off.
+    fn update_macros(&mut self, i: usize, event: SdlEvent) -> () {
+        let (physical, pressed): (PhysicalButton, bool) = match event {
+            SdlEvent::ButtonPress { timestamp: _, which: _, button, pressed }
+                => (PhysicalButton::from_sdl(button), pressed),
+            SdlEvent::AxisMotion { timestamp: _, which: _, axis, value }
+                if axis.is_trigger() => {
+                    // Triggers are held past the same threshold they're
+                    // pressed at when mapped to buttons.
+                    let axis_map: AxisMap = self.gamepads[i].get_axis_map();
+                    let threshold: f32 = AxisMap::to_axis_value(
+                        match axis {
+                            SdlAxis::TriggerLeft
+                                => axis_map.left_trigger_threshold,
+                            _ => axis_map.right_trigger_threshold
+                        });
+                    (
+                        PhysicalButton::from_trigger(axis).unwrap(),
+                        value as f32 > threshold
+                    )
+                },
+            _ => return
+        };
+        let before: u32 = self.held[i];
+        if pressed {
+            self.held[i] |= 1 << (physical as u32);
+        } else {
+            self.held[i] &= !(1 << (physical as u32));
+        }
+
+        for k in 0..self.macros.len() {
+            let mask: u32 = self.macros[k].binding.get_trigger_mask();
+            // Only the press that completes the trigger sets it off.
+            if self.macros[k].i != i
+                || mask == 0
+                || before & mask == mask
+                || self.held[i] & mask != mask {
+                continue;
+            }
+            match self.macros[k].running {
+                Some(id) if self.macros[k].binding.interrupt => {
+                    self.cancel_script(id);
+                },
+                Some(_) => (),
+                None => self.start_macro(k)
+            }
+        }
+    }
+
+    /// Whether a macro is running on a gamepad that's blocking its controllers.
+    fn is_blocked(&self, i: usize) -> bool {
+        return self.macros.iter().any(|bound| bound.i == i
+            && bound.binding.block_input
+            && bound.running.is_some());
+    }
+
+    /// Whether any gamepad that a controller drives is blocked.
+    fn is_which_blocked(&self, which: usize) -> bool {
+        return self.indices.get(&which).map_or(false, |i| self.is_blocked(*i))
+            || self.bindings.iter().any(|binding| binding.which == which
+                && self.is_blocked(binding.i));
+    }
+
+    /// Holds on to an event that a macro blocked, in place of any older one
+    /// for the same input.
+    fn block(&mut self, event: SdlEvent) -> () {
+        self.blocked.retain(|blocked| !is_same_input(*blocked, event));
+        self.blocked.push(event);
+    }
+
+    /// Puts blocked events back in the buffer once nothing's blocking the
+    /// controller they came from anymore.
+    fn unblock(&mut self) -> () {
+        let (unblocked, blocked): (Vec<SdlEvent>, Vec<SdlEvent>)
+            = std::mem::take(&mut self.blocked)
+                .into_iter()
+                .partition(|event| !self.is_which_blocked(
+                    get_event_which(*event)));
+        self.blocked = blocked;
+        for event in unblocked {
+            self.buffer.insert(0, (event, None, 0));
+        }
     }
 
     fn get_indices(&self, event: SdlEvent) -> Option<&usize> {
-        let i: u32;
-        match event {
-            SdlEvent::ControllerAdded { timestamp: _, which }
-                => i = which,
-            SdlEvent::ControllerRemoved { timestamp: _, which }
-                => i = which,
-            SdlEvent::AxisMotion { timestamp: _, which, axis: _, value: _ }
-                => i = which,
-            SdlEvent::ButtonPress { timestamp: _, which, button: _, pressed: _ }
-                => i = which
+        return self.indices.get(&get_event_which(event));
+    }
+
+    /// Updates every binding for the controller that the event came from,
+    /// returning whether there were any.
+    fn update_bindings(&mut self, event: SdlEvent) -> bool {
+        let which: usize = get_event_which(event);
+        let mut found: bool = false;
+        for k in 0..self.bindings.len() {
+            if self.bindings[k].which != which {
+                continue;
+            }
+            found = true;
+            if !self.bindings[k].filter.allows(event) {
+                continue;
+            }
+            let i: usize = self.bindings[k].i;
+            self.update_macros(i, event);
+            if self.is_blocked(i) {
+                self.block(event);
+                continue;
+            }
+            // Settings are only ever changed on the gamepad that the
+            // controller is bound to, so they're copied over first.
+            let gamepad_type: GamepadType
+                = *self.gamepads[i].get_gamepad_type();
+            let settings: GamepadSettings = self.gamepads[i].get_settings();
+            let gamepad: &mut Gamepad = &mut self.bindings[k].gamepad;
+            gamepad.connect(gamepad_type);
+            gamepad.set_settings(settings);
+            if let Err(e) = gamepad.update(event) {
+                eprintln!("{}", e);
+            }
+        }
+        return found;
+    }
+
+    /// Returns the "which" that's been assigned to a gamepad slot, if any.
+    fn get_which(&self, i: usize) -> Option<usize> {
+        for (which, j) in self.indices.iter() {
+            if j == &i {
+                return Some(*which);
+            }
+        }
+        return None;
+    }
+
+    /// Stores a gamepad's settings under the device behind it, if we know
+    /// what it is.
+    fn remember_settings(&mut self, i: usize) -> () {
+        if let Some(device) = self.get_device(i) {
+            self.device_settings.insert(
+                device.get_key(), self.gamepads[i].get_settings());
         }
-        return self.indices.get(&(i as usize));
     }
 
     fn connect(&mut self, which: u32) -> () {
+        if self.indices.contains_key(&(which as usize))
+            || self.bindings
+                .iter()
+                .any(|binding| binding.which == which as usize) {
+            return;
+        }
+        // Take the first slot that isn't already in use.
         for i in 0..NUM_GAMEPADS {
-            if let None = self.gamepads.get(which as usize) {
+            if let GamepadType::Disconnected
+                = self.gamepads[i].get_gamepad_type() {
                 self.gamepads[i].connect(GamepadType::SwitchProController);
-                self.indices.insert(which as usize, i); 
+                self.gamepads[i].set_device_buttons(
+                    self.get_device_buttons(which as usize));
+                self.indices.insert(which as usize, i);
+                self.filters[i] = InputFilter::All;
+                let maybe_settings: Option<GamepadSettings> = self
+                    .get_device(i)
+                    .and_then(|device| self.device_settings
+                        .get(&device.get_key())
+                        .cloned());
+                if let Some(settings) = maybe_settings {
+                    self.gamepads[i].set_settings(settings);
+                }
+                self.events.push(ApplicationEvent::ControllerAdded { i: i });
+                return;
             }
         }
     }
 
     fn disconnect(&mut self, which: u32) -> () {
-        self.gamepads[*self.indices.get(&(which as usize)).unwrap()]
-            .disconnect();
-        self.indices.remove(&(which as usize));
+        self.devices.remove(&(which as usize));
+        self.blocked.retain(|event| get_event_which(*event) != which as usize);
+        self.remove_bindings(which as usize);
+        if let Some(i) = self.indices.remove(&(which as usize)) {
+            self.filters[i] = InputFilter::All;
+            self.held[i] = 0;
+            // If anything was merged into this gamepad, the first of those
+            // takes it over instead. A controller that's already driving a
+            // gamepad of its own (like half of a split one) can't be put in
+            // the indices twice, so it stays bound.
+            let maybe_k: Option<usize> = self.bindings
+                .iter()
+                .position(|binding| binding.i == i
+                    && !self.indices.contains_key(&binding.which));
+            if let Some(k) = maybe_k {
+                let binding: Binding = self.bindings.remove(k);
+                self.indices.insert(binding.which, i);
+                self.filters[i] = binding.filter;
+                self.gamepads[i] = binding.gamepad;
+                return;
+            }
+            if self.bindings.iter().any(|binding| binding.i == i) {
+                // Whatever's left drives it through bindings, so only what
+                // this controller was holding goes.
+                let gamepad_type: GamepadType
+                    = *self.gamepads[i].get_gamepad_type();
+                let settings: GamepadSettings
+                    = self.gamepads[i].get_settings();
+                self.gamepads[i] = Gamepad::new();
+                self.gamepads[i].connect(gamepad_type);
+                self.gamepads[i].set_settings(settings);
+                return;
+            }
+            self.gamepads[i].disconnect();
+            self.events.push(ApplicationEvent::ControllerRemoved { i: i });
+        }
+    }
+
+    /// Forgets every binding for a controller, then disconnects any gamepad
+    /// that's left with nothing driving it.
+    fn remove_bindings(&mut self, which: usize) -> () {
+        let (removed, kept): (Vec<Binding>, Vec<Binding>) = self.bindings
+            .drain(..)
+            .partition(|binding| binding.which == which);
+        self.bindings = kept;
+        for binding in removed {
+            let i: usize = binding.i;
+            let driven: bool = self.get_which(i).is_some()
+                || self.bindings.iter().any(|binding| binding.i == i);
+            if !driven && self.is_connected(i) {
+                self.gamepads[i].disconnect();
+                self.events.push(ApplicationEvent::ControllerRemoved { i: i });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn manager() -> GamepadManager {
+        return GamepadManager::new(
+            Duration::from_millis(16),
+            ScriptStore::new(Path::new("./nonexistent.toml")));
+    }
+
+    fn added(which: u32) -> SdlEvent {
+        return SdlEvent::ControllerAdded { timestamp: 0, which: which };
+    }
+
+    fn press(which: u32, button: SdlButton, pressed: bool) -> SdlEvent {
+        return SdlEvent::ButtonPress {
+            timestamp: 0,
+            which: which,
+            button: button,
+            pressed: pressed
+        }
+    }
+
+    fn removed(which: u32) -> SdlEvent {
+        return SdlEvent::ControllerRemoved { timestamp: 0, which: which };
+    }
+
+    fn axis(which: u32, axis: SdlAxis, value: i16) -> SdlEvent {
+        return SdlEvent::AxisMotion {
+            timestamp: 0,
+            which: which,
+            axis: axis,
+            value: value
+        }
+    }
+
+    fn update(manager: &mut GamepadManager, events: &[SdlEvent]) -> () {
+        manager.update(&mut events.to_vec());
+    }
+
+    fn pressed(manager: &GamepadManager, i: usize) -> Vec<GamepadButton> {
+        return manager.get_gamepads()[i].get_pressed();
+    }
+
+    fn macro_on(trigger: &[PhysicalButton], frames: usize) -> MacroBinding {
+        return MacroBinding {
+            trigger: trigger.to_vec(),
+            script: vec!(ScriptEvent::Wait { frames: frames }),
+            script_name: None,
+            interrupt: false,
+            loop_while_held: false,
+            block_input: false
+        }
+    }
+
+    #[test]
+    fn blocking_macros_catch_up_when_they_finish() {
+        let mut manager: GamepadManager = manager();
+        update(&mut manager, &[added(0)]);
+        let mut binding: MacroBinding
+            = macro_on(&[PhysicalButton::Back, PhysicalButton::Start], 3);
+        binding.block_input = true;
+        manager.set_macro(0, binding).unwrap();
+
+        update(&mut manager, &[
+            press(0, SdlButton::Back, true),
+            axis(0, SdlAxis::LeftX, 20000)
+        ]);
+        update(&mut manager, &[press(0, SdlButton::Start, true)]);
+        // Everything that happens while the macro runs is held back.
+        update(&mut manager, &[
+            press(0, SdlButton::Back, false),
+            axis(0, SdlAxis::LeftX, 0),
+            press(0, SdlButton::A, true)
+        ]);
+        assert!(pressed(&manager, 0) == vec!(GamepadButton::Minus));
+        assert_eq!(
+            manager.get_gamepads()[0].get_left_stick().get_position(),
+            (20000, 0));
+
+        for _ in 0..6 {
+            update(&mut manager, &[]);
+        }
+        // Then it catches up, including the trigger that's still held.
+        assert!(pressed(&manager, 0)
+            == vec!(GamepadButton::B, GamepadButton::Plus));
+        assert_eq!(
+            manager.get_gamepads()[0].get_left_stick().get_position(),
+            (0, 0));
+    }
+
+    #[test]
+    fn devices_that_map_their_own_buttons_keep_them() {
+        let mut manager: GamepadManager = manager();
+        let mut buttons: ButtonMap = ButtonMap::default();
+        buttons.set(PhysicalButton::Paddle1, &[GamepadButton::SLL]);
+        manager.identify(0, DeviceInfo {
+            guid: String::new(),
+            name: String::from("Keyboard and mouse"),
+            buttons: Some(buttons)
+        });
+        update(&mut manager, &[added(0)]);
+        let mut remapped: ButtonMap = manager.get_button_map(0);
+        remapped.set(PhysicalButton::Paddle1, &[GamepadButton::A]);
+        manager.set_button_map(0, remapped);
+
+        update(&mut manager, &[press(0, SdlButton::Paddle1, true)]);
+        assert!(pressed(&manager, 0) == vec!(GamepadButton::SLL));
+    }
+
+    #[test]
+    fn merges_can_split_a_gamepad_between_players() {
+        let mut manager: GamepadManager = manager();
+        update(&mut manager, &[added(0), added(1)]);
+        assert!(manager.merge(
+            0, 1, InputFilter::LeftStick, InputFilter::Buttons));
+
+        update(&mut manager, &[
+            axis(0, SdlAxis::LeftX, 20000),
+            press(0, SdlButton::A, true),
+            axis(1, SdlAxis::LeftX, -20000),
+            press(1, SdlButton::B, true)
+        ]);
+        assert!(pressed(&manager, 0) == vec!(GamepadButton::A));
+        assert_eq!(
+            manager.get_gamepads()[0].get_left_stick().get_position(),
+            (20000, 0));
+
+        // Once they're unmerged, both drive all of their own again.
+        manager.unmerge(0);
+        update(&mut manager, &[
+            press(0, SdlButton::Y, true),
+            axis(1, SdlAxis::LeftX, -20000)
+        ]);
+        assert!(pressed(&manager, 0) == vec!(GamepadButton::X));
+        assert_eq!(
+            manager.get_gamepads()[1].get_left_stick().get_position(),
+            (-20000, 0));
+    }
+
+    #[test]
+    fn merging_brings_macros_along_and_cancels_scripts() {
+        let mut manager: GamepadManager = manager();
+        update(&mut manager, &[added(0), added(1)]);
+        manager.set_macro(0, macro_on(&[PhysicalButton::Back], 5)).unwrap();
+        manager.set_macro(1, macro_on(&[PhysicalButton::Back], 5)).unwrap();
+        manager.set_macro(1, macro_on(&[PhysicalButton::Start], 5)).unwrap();
+        update(&mut manager, &[press(1, SdlButton::Start, true)]);
+        assert_eq!(manager.get_running_scripts().len(), 1);
+
+        assert!(manager.merge(0, 1, InputFilter::All, InputFilter::All));
+        assert_eq!(manager.get_running_scripts().len(), 0);
+        assert_eq!(manager.get_macros(0).len(), 2);
+        assert_eq!(manager.get_macros(1).len(), 0);
+
+        // Start was already held, so letting go of it and pressing it again
+        // is what sets the macro off from its new gamepad.
+        update(&mut manager, &[press(1, SdlButton::Start, false)]);
+        update(&mut manager, &[press(1, SdlButton::Start, true)]);
+        let running: Vec<ScriptStatus> = manager.get_running_scripts();
+        assert_eq!(running.len(), 1);
+        assert_eq!(running[0].i, 0);
+    }
+
+    #[test]
+    fn merged_controllers_drive_one_gamepad() {
+        let mut manager: GamepadManager = manager();
+        update(&mut manager, &[added(0), added(1)]);
+        assert!(!manager.merge(0, 0, InputFilter::All, InputFilter::All));
+        assert!(!manager.merge(0, 2, InputFilter::All, InputFilter::All));
+        assert!(manager.merge(0, 1, InputFilter::All, InputFilter::All));
+        assert!(!manager.is_connected(1));
+
+        update(&mut manager, &[
+            axis(0, SdlAxis::LeftX, 10000),
+            axis(1, SdlAxis::LeftX, 15000),
+            press(0, SdlButton::A, true),
+            press(1, SdlButton::B, true)
+        ]);
+        assert!(pressed(&manager, 0)
+            == vec!(GamepadButton::A, GamepadButton::B));
+        assert_eq!(
+            manager.get_gamepads()[0].get_left_stick().get_position(),
+            (25000, 0));
+        manager.set_merge_policy(0, MergePolicy::Furthest);
+        assert_eq!(
+            manager.get_gamepads()[0].get_left_stick().get_position(),
+            (15000, 0));
+
+        // The controller that was merged in takes over once the gamepad's own
+        // one goes.
+        update(&mut manager, &[removed(0)]);
+        assert!(manager.is_connected(0));
+        assert!(pressed(&manager, 0) == vec!(GamepadButton::A));
+        assert_eq!(
+            manager.get_gamepads()[0].get_left_stick().get_position(),
+            (15000, 0));
+    }
+
+    #[test]
+    fn splitting_gives_each_half_a_gamepad_of_its_own() {
+        let mut manager: GamepadManager = manager();
+        update(&mut manager, &[added(0)]);
+        assert!(!manager.split(0, 0));
+        assert!(manager.split(0, 1));
+        update(&mut manager, &[added(2)]);
+        assert!(!manager.merge(2, 1, InputFilter::All, InputFilter::All));
+
+        // Each half is turned sideways, and the left trigger becomes SR.
+        update(&mut manager, &[
+            press(0, SdlButton::DPadUp, true),
+            press(0, SdlButton::A, true),
+            axis(0, SdlAxis::LeftX, 20000),
+            axis(0, SdlAxis::RightX, 20000),
+            axis(0, SdlAxis::TriggerLeft, i16::MAX)
+        ]);
+        let gamepads: [Gamepad; NUM_GAMEPADS] = manager.get_gamepads();
+        assert!(*gamepads[0].get_gamepad_type()
+            == GamepadType::SidewaysLeftJoyCon);
+        assert!(*gamepads[1].get_gamepad_type()
+            == GamepadType::SidewaysRightJoyCon);
+        assert!(gamepads[0].get_pressed()
+            == vec!(GamepadButton::DR, GamepadButton::SRL));
+        assert!(gamepads[1].get_pressed() == vec!(GamepadButton::A));
+        assert_eq!(gamepads[0].get_left_stick().get_position(), (0, 20000));
+        assert_eq!(
+            gamepads[1].get_right_stick().get_position(), (0, -20000));
+
+        assert!(manager.unsplit(0));
+        assert!(!manager.unsplit(0));
+        assert!(!manager.is_connected(1));
+        assert!(*manager.get_gamepads()[0].get_gamepad_type()
+            == GamepadType::SwitchProController);
+    }
+
+    #[test]
+    fn chords_set_macros_off_once_every_button_is_held() {
+        let mut manager: GamepadManager = manager();
+        update(&mut manager, &[added(0)]);
+        let mut binding: MacroBinding = macro_on(
+            &[PhysicalButton::LeftShoulder, PhysicalButton::RightShoulder], 0);
+        binding.script = vec!(ScriptEvent::Tap {
+            button: SdlButton::A,
+            frames: 2
+        });
+        manager.set_macro(0, binding).unwrap();
+
+        update(&mut manager, &[press(0, SdlButton::LeftShoulder, true)]);
+        assert_eq!(manager.get_running_scripts().len(), 0);
+        update(&mut manager, &[press(0, SdlButton::RightShoulder, true)]);
+        assert_eq!(manager.get_running_scripts().len(), 1);
+        // What the script presses goes through on the next frame.
+        update(&mut manager, &[]);
+        assert!(pressed(&manager, 0)
+            == vec!(GamepadButton::B, GamepadButton::L, GamepadButton::R));
+
+        for _ in 0..3 {
+            update(&mut manager, &[]);
+        }
+        assert_eq!(manager.get_running_scripts().len(), 0);
+        assert!(pressed(&manager, 0)
+            == vec!(GamepadButton::L, GamepadButton::R));
+    }
+
+    #[test]
+    fn pressing_an_interrupting_trigger_again_stops_its_macro() {
+        let mut manager: GamepadManager = manager();
+        update(&mut manager, &[added(0)]);
+        let mut binding: MacroBinding
+            = macro_on(&[PhysicalButton::Back], 10);
+        binding.interrupt = true;
+        manager.set_macro(0, binding).unwrap();
+
+        update(&mut manager, &[press(0, SdlButton::Back, true)]);
+        update(&mut manager, &[press(0, SdlButton::Back, false)]);
+        assert_eq!(manager.get_running_scripts().len(), 1);
+        manager.take_events();
+
+        update(&mut manager, &[press(0, SdlButton::Back, true)]);
+        assert_eq!(manager.get_running_scripts().len(), 0);
+        assert!(manager.take_events().iter().any(|event| matches!(event,
+            ApplicationEvent::ScriptCancelled { i: 0, handle: _ })));
+    }
+
+    #[test]
+    fn looping_macros_run_again_for_as_long_as_the_trigger_is_held() {
+        let mut manager: GamepadManager = manager();
+        update(&mut manager, &[added(0)]);
+        let mut binding: MacroBinding
+            = macro_on(&[PhysicalButton::Back], 1);
+        binding.loop_while_held = true;
+        manager.set_macro(0, binding).unwrap();
+
+        update(&mut manager, &[press(0, SdlButton::Back, true)]);
+        let first: usize = manager.get_running_scripts()[0].handle;
+        for _ in 0..4 {
+            update(&mut manager, &[]);
+        }
+        let running: Vec<ScriptStatus> = manager.get_running_scripts();
+        assert_eq!(running.len(), 1);
+        assert!(running[0].handle > first);
+
+        update(&mut manager, &[press(0, SdlButton::Back, false)]);
+        for _ in 0..4 {
+            update(&mut manager, &[]);
+        }
+        assert_eq!(manager.get_running_scripts().len(), 0);
+    }
+
+    #[test]
+    fn cancelling_a_script_lets_go_of_what_it_touched() {
+        let mut manager: GamepadManager = manager();
+        update(&mut manager, &[added(0)]);
+        let id: usize = manager.run_script(0, &[
+            ScriptEvent::Hold { button: SdlButton::A, frames: 10 },
+            ScriptEvent::AxisMotion { axis: SdlAxis::LeftX, value: 20000 },
+            ScriptEvent::Wait { frames: 10 }
+        ]).unwrap();
+        update(&mut manager, &[]);
+        assert!(pressed(&manager, 0) == vec!(GamepadButton::B));
+        assert_eq!(
+            manager.get_gamepads()[0].get_left_stick().get_position(),
+            (20000, 0));
+        let running: Vec<ScriptStatus> = manager.get_running_scripts();
+        assert_eq!(running[0].handle, id);
+        assert_eq!(running[0].frames_elapsed, 1);
+        assert!(running[0].frames_remaining > 0);
+        manager.take_events();
+
+        assert!(manager.cancel_script(id));
+        assert!(!manager.cancel_script(id));
+        assert!(manager.take_events().iter().any(|event| matches!(event,
+            ApplicationEvent::ScriptCancelled { i: 0, handle } if *handle == id
+        )));
+        // Nothing it hadn't done yet goes through later on.
+        for _ in 0..12 {
+            update(&mut manager, &[]);
+        }
+        assert!(pressed(&manager, 0) == vec!());
+        assert_eq!(
+            manager.get_gamepads()[0].get_left_stick().get_position(),
+            (0, 0));
+        assert_eq!(manager.get_running_scripts().len(), 0);
     }
 }
@@ -1,3 +1,12 @@
+pub mod axis_map;
+pub mod button_map;
+pub mod device_info;
 pub mod gamepad;
+pub mod input_filter;
+pub mod macro_binding;
 pub mod manager;
 pub mod script_event;
+pub mod script_status;
+pub mod script_text;
+pub mod snapshot;
+pub mod turbo;
@@ -1,9 +1,197 @@
-use sdl_event_server::{SdlAxis, SdlButton};
+use std::time::Duration;
+
+use sdl_event_server::{SdlAxis, SdlButton, SdlEvent};
 use serde::{Serialize, Deserialize};
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
+/// The most presses and motions a single script can expand into, so a huge
+/// Repeat can't eat all of our memory.
+const MAX_EXPANDED_EVENTS: usize = 100_000;
+
+fn default_chord_frames() -> usize {
+    return 1;
+}
+
+fn too_many_events() -> String {
+    return format!(
+        "Scripts can't do more than {} things.", MAX_EXPANDED_EVENTS);
+}
+
+fn too_long() -> String {
+    return String::from("Scripts can't wait that long.");
+}
+
+/// Adds frames to a point in a script, failing rather than overflowing.
+fn after(at: usize, frames: usize) -> Result<usize, String> {
+    return at.checked_add(frames).ok_or_else(too_long);
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub enum ScriptEvent {
     AxisMotion { axis: SdlAxis, value: i16 },
     ButtonPress { button: SdlButton, pressed: bool },
-    Wait { frames: usize }
+    Wait { frames: usize },
+    // Waits for as many frames as it takes to cover that many milliseconds.
+    WaitMs { ms: u64 },
+    // Presses a button, then lets go of it that many frames later, before
+    // going on.
+    Tap { button: SdlButton, frames: usize },
+    // Like a tap, except it goes on right away, so everything after it
+    // happens while the button's held.
+    Hold { button: SdlButton, frames: usize },
+    // Taps every button at once.
+    Chord {
+        buttons: Vec<SdlButton>,
+        #[serde(default = "default_chord_frames")]
+        frames: usize
+    },
+    // Moves an axis from one value to the other, a step every frame.
+    StickTween { axis: SdlAxis, from: i16, to: i16, frames: usize },
+    // Does everything in the body over again, that many times in a row.
+    Repeat { count: usize, body: Vec<ScriptEvent> }
+}
+
+impl ScriptEvent {
+    /// Turns a button press or axis motion into the event that a controller
+    /// with the given "which" would send. Anything else has to be expanded
+    /// first.
+    pub fn to_sdl(&self, which: u32) -> Option<SdlEvent> {
+        match self {
+            Self::AxisMotion { axis, value } => Some(SdlEvent::AxisMotion {
+                timestamp: 0,
+                which: which,
+                axis: *axis,
+                value: *value
+            }),
+            Self::ButtonPress { button, pressed }
+                => Some(SdlEvent::ButtonPress {
+                    timestamp: 0,
+                    which: which,
+                    button: *button,
+                    pressed: *pressed
+                }),
+            _ => None
+        }
+    }
+
+    /// Expands a script into nothing but button presses and axis motions,
+    /// each with how many frames into the script it happens, along with how
+    /// many frames the whole script takes.
+    ///
+    /// The frame is how long a single update takes, for waits in
+    /// milliseconds.
+    pub fn expand(
+        script: &[ScriptEvent], frame: Duration
+    ) -> Result<(Vec<(ScriptEvent, usize)>, usize), String> {
+        let mut expanded: Vec<(ScriptEvent, usize)> = vec!();
+        let end: usize = expand_into(script, frame, 0, &mut expanded)?;
+        // Holds can outlast everything after them.
+        let length: usize = expanded
+            .iter()
+            .map(|(_, at)| *at)
+            .fold(end, usize::max);
+        return Ok((expanded, length));
+    }
+}
+
+/// Expands a script that starts at the given frame, returning the frame that
+/// whatever comes after it starts at.
+fn expand_into(
+    script: &[ScriptEvent], frame: Duration, start: usize,
+    expanded: &mut Vec<(ScriptEvent, usize)>
+) -> Result<usize, String> {
+    let mut at: usize = start;
+    for event in script {
+        match event {
+            ScriptEvent::AxisMotion { axis: _, value: _ }
+            | ScriptEvent::ButtonPress { button: _, pressed: _ }
+                => expanded.push((event.clone(), at)),
+            ScriptEvent::Wait { frames } => at = after(at, *frames)?,
+            ScriptEvent::WaitMs { ms } => {
+                let nanos: u128 = frame.as_nanos().max(1);
+                let wait: u128 = Duration::from_millis(*ms).as_nanos();
+                let frames: usize = usize::try_from((wait + nanos - 1) / nanos)
+                    .map_err(|_| too_long())?;
+                at = after(at, frames)?;
+            },
+            ScriptEvent::Tap { button, frames } => {
+                press(expanded, &[*button], at, *frames)?;
+                at = after(at, *frames)?;
+            },
+            ScriptEvent::Hold { button, frames }
+                => press(expanded, &[*button], at, *frames)?,
+            ScriptEvent::Chord { buttons, frames: _ } if buttons.is_empty()
+                => return Err(String::from(
+                    "Chords need at least one button.")),
+            ScriptEvent::Chord { buttons, frames } => {
+                press(expanded, buttons, at, *frames)?;
+                at = after(at, *frames)?;
+            },
+            ScriptEvent::StickTween { axis, from, to, frames } => {
+                let room: usize
+                    = MAX_EXPANDED_EVENTS.saturating_sub(expanded.len());
+                // A tween takes one more event than it has frames.
+                if *frames >= room {
+                    return Err(too_many_events());
+                }
+                let end: usize = after(at, *frames)?;
+                for step in 0..=*frames {
+                    // Full sweeps times many steps don't fit in an i32.
+                    let value: i64 = if *frames == 0 {
+                        *to as i64
+                    } else {
+                        *from as i64 + (*to as i64 - *from as i64)
+                            * step as i64 / *frames as i64
+                    };
+                    expanded.push((
+                        ScriptEvent::AxisMotion {
+                            axis: *axis,
+                            value: value as i16
+                        },
+                        at + step
+                    ));
+                }
+                at = end;
+            },
+            ScriptEvent::Repeat { count, body } => {
+                for k in 0..*count {
+                    let (before, from): (usize, usize) = (expanded.len(), at);
+                    at = expand_into(body, frame, at, expanded)?;
+                    // Bodies that only wait can skip right to the end, rather
+                    // than spinning for as long as the count says.
+                    if expanded.len() == before {
+                        at = (at - from)
+                            .checked_mul(count - k - 1)
+                            .and_then(|rest| at.checked_add(rest))
+                            .ok_or_else(too_long)?;
+                        break;
+                    }
+                }
+            }
+        }
+        if expanded.len() > MAX_EXPANDED_EVENTS {
+            return Err(too_many_events());
+        }
+    }
+    return Ok(at);
+}
+
+/// Presses buttons at a frame, then lets go of them that many frames later.
+fn press(
+    expanded: &mut Vec<(ScriptEvent, usize)>, buttons: &[SdlButton],
+    at: usize, frames: usize
+) -> Result<(), String> {
+    let end: usize = after(at, frames)?;
+    for button in buttons {
+        expanded.push((
+            ScriptEvent::ButtonPress { button: *button, pressed: true },
+            at
+        ));
+    }
+    for button in buttons {
+        expanded.push((
+            ScriptEvent::ButtonPress { button: *button, pressed: false },
+            end
+        ));
+    }
+    return Ok(());
 }
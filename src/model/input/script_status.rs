@@ -0,0 +1,14 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Serialize, Deserialize)]
+/// How far along a running script is, going by the handle it was started
+/// with.
+///
+/// Frames that are left include whatever the script's still waiting on, even
+/// if it has nothing left to press.
+pub struct ScriptStatus {
+    pub handle: usize,
+    pub i: usize,
+    pub frames_elapsed: usize,
+    pub frames_remaining: usize
+}
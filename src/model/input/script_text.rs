@@ -0,0 +1,555 @@
+use crate::model::input::script_event::ScriptEvent;
+use sdl_event_server::{SdlAxis, SdlButton};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Where something went wrong in a script's text, counting from 1.
+pub struct ScriptTextError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String
+}
+
+impl ScriptTextError {
+    pub fn get_message(&self) -> String {
+        return format!(
+            "Line {}, column {}: {}", self.line, self.column, self.message);
+    }
+}
+
+#[derive(Clone)]
+struct Token {
+    text: String,
+    line: usize,
+    column: usize
+}
+
+/// Splits text into words, braces and separators, dropping comments. Both
+/// newlines and semicolons end a statement, so they come out the same.
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens: Vec<Token> = vec!();
+    for (l, line) in text.lines().enumerate() {
+        let mut word: Option<Token> = None;
+        for (c, character) in line.chars().enumerate() {
+            if character == '#' {
+                break;
+            }
+            let separate: bool = character.is_whitespace()
+                || character == ';' || character == '{' || character == '}';
+            if !separate {
+                word.get_or_insert(Token {
+                    text: String::new(),
+                    line: l + 1,
+                    column: c + 1
+                }).text.push(character);
+                continue;
+            }
+            if let Some(token) = word.take() {
+                tokens.push(token);
+            }
+            if !character.is_whitespace() {
+                tokens.push(Token {
+                    text: character.to_string(),
+                    line: l + 1,
+                    column: c + 1
+                });
+            }
+        }
+        if let Some(token) = word.take() {
+            tokens.push(token);
+        }
+        tokens.push(Token {
+            text: String::from(";"),
+            line: l + 1,
+            column: line.chars().count() + 1
+        });
+    }
+    return tokens;
+}
+
+/// Reads a name the same way it'd be read from JSON.
+fn from_name<T: DeserializeOwned>(name: &str) -> Option<T> {
+    return serde_json::from_value(serde_json::Value::from(name)).ok();
+}
+
+/// Writes a name the same way it'd be written to JSON.
+fn to_name<T: Serialize>(value: &T) -> String {
+    return match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new()
+    };
+}
+
+fn to_axis(name: &str) -> Option<SdlAxis> {
+    return match name {
+        "LX" => Some(SdlAxis::LeftX),
+        "LY" => Some(SdlAxis::LeftY),
+        "RX" => Some(SdlAxis::RightX),
+        "RY" => Some(SdlAxis::RightY),
+        "LT" => Some(SdlAxis::TriggerLeft),
+        "RT" => Some(SdlAxis::TriggerRight),
+        _ => from_name(name)
+    };
+}
+
+/// How deep repeats can be nested inside each other. Each one is parsed (and
+/// later expanded) recursively, so this keeps a script from overflowing the
+/// stack.
+const MAX_REPEAT_DEPTH: usize = 32;
+
+enum Duration {
+    Frames(usize),
+    Ms(u64)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    k: usize,
+    // How many repeats the statement being parsed is inside of.
+    depth: usize
+}
+
+impl Parser {
+    fn error(&self, token: &Token, message: &str) -> ScriptTextError {
+        return ScriptTextError {
+            line: token.line,
+            column: token.column,
+            message: String::from(message)
+        };
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        return self.tokens.get(self.k);
+    }
+
+    /// Takes the next token, which has to be on the same statement.
+    fn next(&mut self, expected: &str) -> Result<Token, ScriptTextError> {
+        match self.tokens.get(self.k) {
+            None => {
+                let last: Token = self.tokens
+                    .last()
+                    .cloned()
+                    .unwrap_or(Token {
+                        text: String::new(),
+                        line: 1,
+                        column: 1
+                    });
+                return Err(self.error(
+                    &last, &format!("Expected {}.", expected)));
+            },
+            Some(token) if token.text == ";" || token.text == "}"
+                => return Err(self.error(token, &format!(
+                    "Expected {}.", expected))),
+            Some(token) => {
+                self.k += 1;
+                return Ok(token.clone());
+            }
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        return match self.peek() {
+            None => true,
+            Some(token) => token.text == ";" || token.text == "}"
+        };
+    }
+
+    fn button(&mut self) -> Result<SdlButton, ScriptTextError> {
+        let token: Token = self.next("a button")?;
+        return from_name(&token.text).ok_or(self.error(
+            &token, &format!("There's no button named \"{}\".", token.text)));
+    }
+
+    fn axis(&mut self) -> Result<SdlAxis, ScriptTextError> {
+        let token: Token = self.next("an axis")?;
+        return to_axis(&token.text).ok_or(self.error(
+            &token, &format!("There's no axis named \"{}\".", token.text)));
+    }
+
+    fn number<T: std::str::FromStr>(
+        &mut self, expected: &str
+    ) -> Result<T, ScriptTextError> {
+        let token: Token = self.next(expected)?;
+        return token.text.parse().map_err(|_| self.error(
+            &token, &format!("Expected {}.", expected)));
+    }
+
+    fn duration(&mut self) -> Result<Duration, ScriptTextError> {
+        let token: Token = self.next("a duration, like 10f or 100ms")?;
+        let parsed: Option<Duration> = if token.text.ends_with("ms") {
+            token.text[..token.text.len() - 2].parse().ok().map(Duration::Ms)
+        } else if token.text.ends_with("f") {
+            token.text[..token.text.len() - 1]
+                .parse()
+                .ok()
+                .map(Duration::Frames)
+        } else {
+            None
+        };
+        return parsed.ok_or(self.error(
+            &token, "Expected a duration, like 10f or 100ms."));
+    }
+
+    fn frames(&mut self) -> Result<usize, ScriptTextError> {
+        let token: Option<Token> = self.peek().cloned();
+        return match self.duration()? {
+            Duration::Frames(frames) => Ok(frames),
+            Duration::Ms(_) => Err(self.error(
+                &token.unwrap(), "Only waits can be in milliseconds."))
+        };
+    }
+
+    /// Parses statements until the end, or until a closing brace if this is
+    /// the body of a repeat.
+    fn script(
+        &mut self, body: bool
+    ) -> Result<Vec<ScriptEvent>, ScriptTextError> {
+        let mut script: Vec<ScriptEvent> = vec!();
+        loop {
+            let token: Token = match self.peek() {
+                None if body => {
+                    let last: Token = self.tokens.last().cloned().unwrap();
+                    return Err(self.error(&last, "Expected a \"}\"."));
+                },
+                None => return Ok(script),
+                Some(token) => token.clone()
+            };
+            self.k += 1;
+            match token.text.as_str() {
+                ";" => continue,
+                "}" if body => return Ok(script),
+                "}" => return Err(self.error(&token, "Nothing to close here.")),
+                _ => self.statement(&token, &mut script)?
+            }
+            if !self.is_done() {
+                let extra: Token = self.peek().cloned().unwrap();
+                return Err(self.error(
+                    &extra, "Expected the end of the statement."));
+            }
+        }
+    }
+
+    fn statement(
+        &mut self, command: &Token, script: &mut Vec<ScriptEvent>
+    ) -> Result<(), ScriptTextError> {
+        match command.text.as_str() {
+            "press" => {
+                let button: SdlButton = self.button()?;
+                if self.is_done() {
+                    script.push(ScriptEvent::ButtonPress {
+                        button: button,
+                        pressed: true
+                    });
+                } else {
+                    script.push(ScriptEvent::Tap {
+                        button: button,
+                        frames: self.frames()?
+                    });
+                }
+            },
+            "release" => script.push(ScriptEvent::ButtonPress {
+                button: self.button()?,
+                pressed: false
+            }),
+            "hold" => script.push(ScriptEvent::Hold {
+                button: self.button()?,
+                frames: self.frames()?
+            }),
+            "chord" => {
+                let mut buttons: Vec<SdlButton> = vec!(self.button()?);
+                let mut frames: usize = 1;
+                while !self.is_done() {
+                    let ends_in_digit: bool = self.peek()
+                        .map_or(false, |token| token.text.starts_with(
+                            |c: char| c.is_ascii_digit()));
+                    if ends_in_digit {
+                        frames = self.frames()?;
+                        break;
+                    }
+                    buttons.push(self.button()?);
+                }
+                script.push(ScriptEvent::Chord {
+                    buttons: buttons,
+                    frames: frames
+                });
+            },
+            "axis" => script.push(ScriptEvent::AxisMotion {
+                axis: self.axis()?,
+                value: self.number("a value from -32768 to 32767")?
+            }),
+            "stick" => {
+                let side: Token = self.next("L or R")?;
+                let (x, y): (SdlAxis, SdlAxis) = match side.text.as_str() {
+                    "L" => (SdlAxis::LeftX, SdlAxis::LeftY),
+                    "R" => (SdlAxis::RightX, SdlAxis::RightY),
+                    _ => return Err(self.error(&side, "Expected L or R."))
+                };
+                let values: (i16, i16) = (
+                    self.number("a value from -32768 to 32767")?,
+                    self.number("a value from -32768 to 32767")?
+                );
+                script.push(
+                    ScriptEvent::AxisMotion { axis: x, value: values.0 });
+                script.push(
+                    ScriptEvent::AxisMotion { axis: y, value: values.1 });
+                // Like pressing a button for a while, the stick goes back to
+                // the middle afterwards.
+                if !self.is_done() {
+                    script.push(ScriptEvent::Wait { frames: self.frames()? });
+                    script.push(ScriptEvent::AxisMotion { axis: x, value: 0 });
+                    script.push(ScriptEvent::AxisMotion { axis: y, value: 0 });
+                }
+            },
+            "tween" => script.push(ScriptEvent::StickTween {
+                axis: self.axis()?,
+                from: self.number("a value from -32768 to 32767")?,
+                to: self.number("a value from -32768 to 32767")?,
+                frames: self.frames()?
+            }),
+            "wait" => script.push(match self.duration()? {
+                Duration::Frames(frames)
+                    => ScriptEvent::Wait { frames: frames },
+                Duration::Ms(ms) => ScriptEvent::WaitMs { ms: ms }
+            }),
+            "repeat" => {
+                if self.depth == MAX_REPEAT_DEPTH {
+                    return Err(self.error(command, &format!(
+                        "Repeats can't be nested more than {} deep.",
+                        MAX_REPEAT_DEPTH)));
+                }
+                let count: usize = self.number("a count")?;
+                match self.tokens.get(self.k) {
+                    Some(token) if token.text == "{" => self.k += 1,
+                    Some(token) => return Err(self.error(
+                        token, "Expected a \"{\".")),
+                    None => return Err(self.error(
+                        command, "Expected a \"{\"."))
+                }
+                self.depth += 1;
+                let body: Vec<ScriptEvent> = self.script(true)?;
+                self.depth -= 1;
+                script.push(ScriptEvent::Repeat {
+                    count: count,
+                    body: body
+                });
+            },
+            _ => return Err(self.error(command, &format!(
+                "\"{}\" isn't something a script can do.", command.text)))
+        }
+        return Ok(());
+    }
+}
+
+/// Parses a script from text, where each line (or each part of one, between
+/// semicolons) does one thing:
+///
+/// ```text
+/// press A 3f          # taps A for 3 frames, or presses it if there's no time
+/// release A
+/// hold B 30f          # holds B while the rest goes on
+/// chord LeftShoulder RightShoulder 2f
+/// axis LX 12000       # LX, LY, RX, RY, LT and RT, or SDL's names
+/// stick L 0 -32768 20f
+/// tween RX 0 32767 10f
+/// wait 10f            # or 100ms
+/// repeat 3 { press A 2f; wait 2f }
+/// ```
+///
+/// Repeats can be nested, but only up to 32 deep.
+pub fn parse(text: &str) -> Result<Vec<ScriptEvent>, ScriptTextError> {
+    let mut parser: Parser = Parser {
+        tokens: tokenize(text),
+        k: 0,
+        depth: 0
+    };
+    return parser.script(false);
+}
+
+/// Writes a script as text that parses back into the same script. Fails on
+/// chords without any buttons, which can't be written down or run.
+pub fn print(script: &[ScriptEvent]) -> Result<String, String> {
+    let mut text: String = String::new();
+    print_into(script, 0, &mut text)?;
+    return Ok(text);
+}
+
+fn print_into(
+    script: &[ScriptEvent], depth: usize, text: &mut String
+) -> Result<(), String> {
+    let indent: String = "    ".repeat(depth);
+    for event in script {
+        let line: String = match event {
+            ScriptEvent::AxisMotion { axis, value }
+                => format!("axis {} {}", to_name(axis), value),
+            ScriptEvent::ButtonPress { button, pressed: true }
+                => format!("press {}", to_name(button)),
+            ScriptEvent::ButtonPress { button, pressed: false }
+                => format!("release {}", to_name(button)),
+            ScriptEvent::Wait { frames } => format!("wait {}f", frames),
+            ScriptEvent::WaitMs { ms } => format!("wait {}ms", ms),
+            ScriptEvent::Tap { button, frames }
+                => format!("press {} {}f", to_name(button), frames),
+            ScriptEvent::Hold { button, frames }
+                => format!("hold {} {}f", to_name(button), frames),
+            ScriptEvent::Chord { buttons, frames: _ } if buttons.is_empty()
+                => return Err(String::from(
+                    "Chords need at least one button.")),
+            ScriptEvent::Chord { buttons, frames } => format!(
+                "chord {} {}f",
+                buttons.iter().map(to_name).collect::<Vec<String>>().join(" "),
+                frames),
+            ScriptEvent::StickTween { axis, from, to, frames } => format!(
+                "tween {} {} {} {}f", to_name(axis), from, to, frames),
+            ScriptEvent::Repeat { count, body } => {
+                text.push_str(&format!("{}repeat {} {{\n", indent, count));
+                print_into(body, depth + 1, text)?;
+                format!("}}")
+            }
+        };
+        text.push_str(&indent);
+        text.push_str(&line);
+        text.push('\n');
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Script events can't be compared directly, but their JSON can.
+    fn to_json(script: &[ScriptEvent]) -> serde_json::Value {
+        return serde_json::to_value(script).unwrap();
+    }
+
+    fn every_event() -> Vec<ScriptEvent> {
+        return vec!(
+            ScriptEvent::AxisMotion { axis: SdlAxis::LeftY, value: -32768 },
+            ScriptEvent::AxisMotion { axis: SdlAxis::TriggerRight, value: 7 },
+            ScriptEvent::ButtonPress { button: SdlButton::A, pressed: true },
+            ScriptEvent::ButtonPress { button: SdlButton::A, pressed: false },
+            ScriptEvent::Wait { frames: 10 },
+            ScriptEvent::WaitMs { ms: 250 },
+            ScriptEvent::Tap { button: SdlButton::DPadUp, frames: 3 },
+            ScriptEvent::Hold { button: SdlButton::Paddle2, frames: 30 },
+            ScriptEvent::Chord {
+                buttons: vec!(
+                    SdlButton::LeftShoulder,
+                    SdlButton::RightShoulder
+                ),
+                frames: 2
+            },
+            ScriptEvent::StickTween {
+                axis: SdlAxis::RightX,
+                from: 32767,
+                to: -32768,
+                frames: 20
+            },
+            ScriptEvent::Repeat {
+                count: 3,
+                body: vec!(
+                    ScriptEvent::Tap { button: SdlButton::B, frames: 1 },
+                    ScriptEvent::Repeat {
+                        count: 2,
+                        body: vec!(ScriptEvent::Wait { frames: 1 })
+                    },
+                    ScriptEvent::Repeat { count: 0, body: vec!() }
+                )
+            }
+        );
+    }
+
+    fn parse_error(text: &str) -> (usize, usize, String) {
+        match parse(text) {
+            Ok(_) => panic!("\"{}\" parsed", text),
+            Err(e) => return (e.line, e.column, e.message)
+        }
+    }
+
+    #[test]
+    fn every_event_round_trips() {
+        for event in every_event() {
+            let script: Vec<ScriptEvent> = vec!(event);
+            let text: String = print(&script).unwrap();
+            match parse(&text) {
+                Ok(parsed) => assert_eq!(to_json(&parsed), to_json(&script)),
+                Err(e) => panic!("{}\n{}", text, e.get_message())
+            }
+        }
+    }
+
+    #[test]
+    fn whole_script_round_trips() {
+        let script: Vec<ScriptEvent> = every_event();
+        let text: String = print(&script).unwrap();
+        let parsed: Vec<ScriptEvent> = parse(&text).ok().unwrap();
+        assert_eq!(to_json(&parsed), to_json(&script));
+        assert_eq!(print(&parsed).unwrap(), text);
+    }
+
+    #[test]
+    fn documented_example_parses() {
+        let text: &str = "press A 3f; release A; hold B 30f\n\
+            chord LeftShoulder RightShoulder 2f\n\
+            axis LX 12000  # a comment\n\
+            stick L 0 -32768 20f\n\
+            tween RX 0 32767 10f\n\
+            wait 10f; wait 100ms\n\
+            repeat 3 { press A 2f; wait 2f }";
+        let parsed: Vec<ScriptEvent> = parse(text).ok().unwrap();
+        // The stick sets both axes, waits, then centers both of them.
+        assert_eq!(parsed.len(), 14);
+    }
+
+    #[test]
+    fn reports_unknown_buttons() {
+        assert_eq!(
+            parse_error("wait 1f\npress Q 3f"),
+            (2, 7, String::from("There's no button named \"Q\".")));
+    }
+
+    #[test]
+    fn reports_missing_braces() {
+        let (line, column, message): (usize, usize, String)
+            = parse_error("repeat 2 {\n    press A 2f");
+        assert_eq!((line, column), (2, 15));
+        assert_eq!(message, "Expected a \"}\".");
+        assert_eq!(parse_error("wait 1f; }").1, 10);
+    }
+
+    #[test]
+    fn limits_how_deep_repeats_nest() {
+        let nested = |depth: usize| format!(
+            "{}wait 1f{}", "repeat 2 { ".repeat(depth), " }".repeat(depth));
+        assert!(parse(&nested(MAX_REPEAT_DEPTH)).is_ok());
+        assert_eq!(
+            parse_error(&nested(MAX_REPEAT_DEPTH + 1)),
+            (1, 11 * MAX_REPEAT_DEPTH + 1, String::from(
+                "Repeats can't be nested more than 32 deep.")));
+        // Way past the limit, it's still an error instead of a crash.
+        assert!(parse(&nested(100000)).is_err());
+    }
+
+    #[test]
+    fn reports_trailing_tokens() {
+        assert_eq!(
+            parse_error("press A 3f extra"),
+            (1, 12, String::from("Expected the end of the statement.")));
+    }
+
+    #[test]
+    fn reports_milliseconds_outside_waits() {
+        assert_eq!(
+            parse_error("wait 5ms\nhold A 3ms"),
+            (2, 8, String::from("Only waits can be in milliseconds.")));
+    }
+
+    #[test]
+    fn rejects_empty_chords() {
+        let script: Vec<ScriptEvent> = vec!(ScriptEvent::Chord {
+            buttons: vec!(),
+            frames: 1
+        });
+        assert!(print(&script).is_err());
+        assert!(parse("chord 1f").is_err());
+    }
+}
@@ -0,0 +1,61 @@
+use crate::model::input::{
+    device_info::DeviceInfo,
+    gamepad::{AnalogStick, Gamepad, GamepadButton, GamepadType},
+    turbo::TurboButton
+};
+use serde::{Serialize, Deserialize};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StickSnapshot {
+    raw: (i16, i16),
+    position: (i16, i16),
+    deadzone: f32
+}
+
+impl StickSnapshot {
+    pub fn new(stick: &AnalogStick) -> StickSnapshot {
+        return StickSnapshot {
+            raw: stick.get_raw_position(),
+            position: stick.get_position(),
+            deadzone: stick.get_deadzone()
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+/// The full state of a single gamepad slot at some point in time.
+///
+/// Sticks include both their raw positions and their positions after the
+/// deadzone was applied, since the latter is what actually gets sent.
+pub struct GamepadSnapshot {
+    i: usize,
+    gamepad_type: GamepadType,
+    device: Option<DeviceInfo>,
+    // The bits that get sent, where Home and Capture share bits with LR and
+    // LD, and every button that's pressed, which doesn't mix them up.
+    buttons: i32,
+    pressed: Vec<GamepadButton>,
+    left_stick: StickSnapshot,
+    right_stick: StickSnapshot,
+    turbo: Vec<TurboButton>,
+    // Turbo buttons that are firing, even on frames where they're released.
+    turbo_firing: Vec<GamepadButton>
+}
+
+impl GamepadSnapshot {
+    pub fn new(
+        i: usize, gamepad: &Gamepad, device: Option<DeviceInfo>
+    ) -> GamepadSnapshot {
+        return GamepadSnapshot {
+            i: i,
+            gamepad_type: *gamepad.get_gamepad_type(),
+            device: device,
+            buttons: gamepad.get_buttons(),
+            pressed: gamepad.get_pressed(),
+            left_stick: StickSnapshot::new(&gamepad.get_left_stick()),
+            right_stick: StickSnapshot::new(&gamepad.get_right_stick()),
+            turbo: gamepad.get_turbo_map().get_buttons(),
+            turbo_firing: gamepad.get_turbo_firing()
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use crate::model::input::gamepad::GamepadButton;
+use serde::{Serialize, Deserialize};
+
+const NUM_BUTTONS: usize = 30;
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+/// How holding down a turbo button makes it fire.
+pub enum TurboMode {
+    // Fires for as long as the button is held.
+    Hold,
+    // Pressing the button starts it firing, and pressing it again stops it.
+    Toggle
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+/// Turbo on a single button, which stays pressed for the given number of
+/// frames, then released for as many, over and over.
+pub struct TurboButton {
+    pub button: GamepadButton,
+    pub mode: TurboMode,
+    pub frames: usize
+}
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Vec<TurboButton>", into = "Vec<TurboButton>")]
+/// Every button on a gamepad that has turbo, along with how it fires.
+///
+/// Like a button map, this is kept small enough to copy around with its
+/// gamepad, and only the buttons with turbo get written out.
+pub struct TurboMap {
+    buttons: [Option<(TurboMode, usize)>; NUM_BUTTONS]
+}
+
+impl Default for TurboMap {
+    fn default() -> TurboMap {
+        return TurboMap {
+            buttons: [None; NUM_BUTTONS]
+        }
+    }
+}
+
+impl TurboMap {
+    pub fn get(&self, button: GamepadButton) -> Option<(TurboMode, usize)> {
+        return self.buttons[button as usize];
+    }
+
+    /// Gives a button turbo, or takes it away with None. A button can't fire
+    /// faster than once every two frames, so zero frames counts as one.
+    pub fn set(
+        &mut self, button: GamepadButton, turbo: Option<(TurboMode, usize)>
+    ) -> () {
+        self.buttons[button as usize]
+            = turbo.map(|(mode, frames)| (mode, frames.max(1)));
+    }
+
+    pub fn get_buttons(&self) -> Vec<TurboButton> {
+        let mut buttons: Vec<TurboButton> = vec!();
+        for button in GamepadButton::ALL {
+            if let Some((mode, frames)) = self.get(button) {
+                buttons.push(TurboButton {
+                    button: button,
+                    mode: mode,
+                    frames: frames
+                });
+            }
+        }
+        return buttons;
+    }
+}
+
+impl From<Vec<TurboButton>> for TurboMap {
+    fn from(buttons: Vec<TurboButton>) -> TurboMap {
+        let mut map: TurboMap = TurboMap::default();
+        for turbo in buttons {
+            map.set(turbo.button, Some((turbo.mode, turbo.frames)));
+        }
+        return map;
+    }
+}
+
+impl From<TurboMap> for Vec<TurboButton> {
+    fn from(map: TurboMap) -> Vec<TurboButton> {
+        return map.get_buttons();
+    }
+}
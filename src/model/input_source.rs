@@ -0,0 +1,181 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::AtomicBool
+    },
+    time::{Duration, Instant}
+};
+
+use crate::model::{
+    application_event::ApplicationEvent,
+    input::device_info::DeviceInfo,
+    remote_interface::{RemoteConfig, RemoteSource},
+    sdl_interface::SdlServerSource
+};
+#[cfg(target_os = "linux")]
+use crate::model::{
+    evdev_interface::EvdevSource,
+    keyboard_mouse::KeyboardMouseConfig,
+    keyboard_mouse_interface::KeyboardMouseSource
+};
+use crossbeam_channel::{Receiver, Sender};
+use sdl_event_server::SdlEvent;
+
+/// How many bits of "which" each input source gets to itself. The rest say
+/// which source it came from.
+const SOURCE_SHIFT: u32 = 24;
+
+/// Where evdev devices are found on Linux.
+pub const INPUT_DIRECTORY: &str = "/dev/input";
+
+/// The input sources that can be used, alongside each other.
+#[derive(Clone)]
+pub enum InputConfig {
+    SdlServer { path: PathBuf },
+    // Both of these are only supported on Linux, so asking for them anywhere
+    // else is turned away when the options are checked.
+    #[cfg(target_os = "linux")]
+    Evdev { directory: PathBuf },
+    #[cfg(target_os = "linux")]
+    KeyboardMouse { directory: PathBuf, config: KeyboardMouseConfig },
+    Remote { config: RemoteConfig }
+}
+
+impl InputConfig {
+    /// Creates the source that this describes. The frame is how long a single
+    /// update takes.
+    pub fn into_source(
+        self, ticks: Receiver<Instant>, frame: Duration, done: Arc<AtomicBool>
+    ) -> Box<dyn InputSource> {
+        return match self {
+            InputConfig::SdlServer { path } => Box::new(
+                SdlServerSource::new(&path, done)),
+            #[cfg(target_os = "linux")]
+            InputConfig::Evdev { directory } => Box::new(
+                EvdevSource::new(&directory, done)),
+            #[cfg(target_os = "linux")]
+            InputConfig::KeyboardMouse { directory, config } => Box::new(
+                KeyboardMouseSource::new(config, ticks, &directory, done)),
+            InputConfig::Remote { config } => Box::new(
+                RemoteSource::new(config, ticks, frame, done))
+        };
+    }
+}
+
+/// Where an input source puts everything it reads, until the next update.
+///
+/// Each source gets its own copy, which moves the "which" of every event into
+/// a range that only that source uses, so sources don't need to know about
+/// each other.
+#[derive(Clone)]
+pub struct InputBuffer {
+    source: u32,
+    events_mtx: Arc<Mutex<Vec<SdlEvent>>>,
+    devices_mtx: Arc<Mutex<Vec<(u32, DeviceInfo)>>>,
+    events_tx: Sender<ApplicationEvent>
+}
+
+impl InputBuffer {
+    pub fn new(events_tx: Sender<ApplicationEvent>) -> InputBuffer {
+        return InputBuffer {
+            source: 0,
+            events_mtx: Arc::new(Mutex::new(vec!())),
+            devices_mtx: Arc::new(Mutex::new(vec!())),
+            events_tx: events_tx
+        }
+    }
+
+    /// Returns a copy of this buffer for the given source to write into.
+    pub fn for_source(&self, source: usize) -> InputBuffer {
+        let mut buffer: InputBuffer = self.clone();
+        buffer.source = source as u32;
+        return buffer;
+    }
+
+    fn to_global(&self, which: u32) -> u32 {
+        return (self.source << SOURCE_SHIFT) | which;
+    }
+
+    pub fn push_event(&self, event: SdlEvent) -> Result<(), String> {
+        let event: SdlEvent = match event {
+            SdlEvent::ControllerAdded { timestamp, which }
+                => SdlEvent::ControllerAdded {
+                    timestamp: timestamp,
+                    which: self.to_global(which)
+                },
+            SdlEvent::ControllerRemoved { timestamp, which }
+                => SdlEvent::ControllerRemoved {
+                    timestamp: timestamp,
+                    which: self.to_global(which)
+                },
+            SdlEvent::AxisMotion { timestamp, which, axis, value }
+                => SdlEvent::AxisMotion {
+                    timestamp: timestamp,
+                    which: self.to_global(which),
+                    axis: axis,
+                    value: value
+                },
+            SdlEvent::ButtonPress { timestamp, which, button, pressed }
+                => SdlEvent::ButtonPress {
+                    timestamp: timestamp,
+                    which: self.to_global(which),
+                    button: button,
+                    pressed: pressed
+                }
+        };
+        if let Ok(mut events) = self.events_mtx.lock() {
+            events.push(event);
+            return Ok(());
+        } else {
+            return Err(String::from("Failed to lock events."));
+        }
+    }
+
+    /// Says what the device behind "which" is. This should be pushed before
+    /// the device's ControllerAdded event.
+    pub fn push_device(
+        &self, which: u32, device: DeviceInfo
+    ) -> Result<(), String> {
+        if let Ok(mut devices) = self.devices_mtx.lock() {
+            devices.push((self.to_global(which), device));
+            return Ok(());
+        } else {
+            return Err(String::from("Failed to lock devices."));
+        }
+    }
+
+    /// Lets a source report things that happen to it, like its process exiting.
+    pub fn send(&self, event: ApplicationEvent) -> () {
+        self.events_tx.send(event).unwrap_or(());
+    }
+
+    /// Takes everything pushed since the last call.
+    ///
+    /// The events are taken before the devices, since devices are pushed before
+    /// their events; this way, a controller is never seen before we know what
+    /// it is.
+    pub fn take(&self) -> (Vec<SdlEvent>, Vec<(u32, DeviceInfo)>) {
+        let events: Vec<SdlEvent> = match self.events_mtx.lock() {
+            Err(_) => vec!(),
+            Ok(mut events) => events.drain(..).collect()
+        };
+        let devices: Vec<(u32, DeviceInfo)> = match self.devices_mtx.lock() {
+            Err(_) => vec!(),
+            Ok(mut devices) => devices.drain(..).collect()
+        };
+        return (events, devices);
+    }
+}
+
+/// Something that controller input comes from, like the SDL server.
+///
+/// Sources read on threads of their own, pushing whatever they read into the
+/// buffer they're started with. The gamepad manager only ever sees the events,
+/// so adding a source doesn't mean touching it.
+pub trait InputSource: Send {
+    fn start(&mut self, buffer: InputBuffer) -> Result<(), String>;
+
+    /// Tells the source to stop reading, like when we're exiting.
+    fn stop(&mut self) -> Result<(), String>;
+}
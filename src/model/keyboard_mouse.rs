@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use crate::model::input::gamepad::GamepadButton;
+use serde::{Serialize, Deserialize};
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StickSide {
+    Left,
+    Right
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+/// How a keyboard and mouse act as a gamepad.
+///
+/// Keys are named the same way evdev names them, like "KEY_SPACE", and mouse
+/// buttons are keys too ("BTN_LEFT"). Mapping a key to a stick direction, like
+/// LU, pushes that stick all the way in that direction. Keys press the button
+/// they're mapped to, however the gamepad's own button map is set up.
+pub struct KeyboardMouseConfig {
+    pub keys: HashMap<String, GamepadButton>,
+    /// The stick that moving the mouse moves.
+    pub mouse_stick: StickSide,
+    /// How much of the way the stick moves for each unit the mouse moves.
+    pub sensitivity: f32,
+    /// How much of the mouse's push on the stick is kept every update, so the
+    /// stick recenters once the mouse stops. Should be between 0 and 1.
+    pub decay: f32,
+    /// Whether to keep the keyboard and mouse to ourselves, so whatever else
+    /// is running doesn't also see the input.
+    pub grab: bool
+}
+
+impl Default for KeyboardMouseConfig {
+    fn default() -> KeyboardMouseConfig {
+        let mut keys: HashMap<String, GamepadButton> = HashMap::new();
+        for (key, button) in [
+            ("KEY_W", GamepadButton::LU),
+            ("KEY_A", GamepadButton::LL),
+            ("KEY_S", GamepadButton::LD),
+            ("KEY_D", GamepadButton::LR),
+            ("KEY_SPACE", GamepadButton::B),
+            ("KEY_E", GamepadButton::A),
+            ("KEY_Q", GamepadButton::Y),
+            ("KEY_R", GamepadButton::X),
+            ("KEY_LEFTSHIFT", GamepadButton::LST),
+            ("KEY_LEFTCTRL", GamepadButton::L),
+            ("KEY_F", GamepadButton::R),
+            ("BTN_RIGHT", GamepadButton::ZL),
+            ("BTN_LEFT", GamepadButton::ZR),
+            ("BTN_MIDDLE", GamepadButton::RST),
+            ("KEY_UP", GamepadButton::DU),
+            ("KEY_DOWN", GamepadButton::DD),
+            ("KEY_LEFT", GamepadButton::DL),
+            ("KEY_RIGHT", GamepadButton::DR),
+            ("KEY_ENTER", GamepadButton::Plus),
+            ("KEY_TAB", GamepadButton::Minus)
+        ] {
+            keys.insert(String::from(key), button);
+        }
+
+        return KeyboardMouseConfig {
+            keys: keys,
+            mouse_stick: StickSide::Right,
+            sensitivity: 0.02,
+            decay: 0.8,
+            grab: false
+        }
+    }
+}
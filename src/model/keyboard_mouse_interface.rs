@@ -0,0 +1,450 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering}
+    },
+    thread,
+    time::Instant
+};
+
+use crate::model::{
+    evdev_interface::watch_devices,
+    input::{
+        button_map::{ButtonMap, PhysicalButton},
+        device_info::DeviceInfo,
+        gamepad::GamepadButton
+    },
+    input_source::{InputBuffer, InputSource},
+    keyboard_mouse::{KeyboardMouseConfig, StickSide}
+};
+use crossbeam_channel::{select, Receiver};
+use evdev::{Device, InputEvent, InputEventKind, Key, RelativeAxisType};
+use sdl_event_server::{SdlAxis, SdlButton, SdlEvent};
+
+/// The keyboard and mouse only ever make up a single gamepad.
+const WHICH: u32 = 0;
+
+/// The SDL input that a key pretends to be to press a button.
+#[derive(Copy, Clone)]
+enum KeyTarget {
+    Button(SdlButton),
+    Trigger(SdlAxis),
+    // An axis, and whether the key pushes it the positive way.
+    Stick(SdlAxis, bool)
+}
+
+/// Returns the SDL input that keys send to press the given button.
+///
+/// Every button gets an input of its own, which the keyboard's own button map
+/// turns back into that button, so keys press what they're mapped to however
+/// the gamepad's mapped and whatever it's emulating. Mostly, these are the
+/// inputs that would press the same button on a Pro Controller anyway.
+fn to_target(button: GamepadButton) -> KeyTarget {
+    let target: KeyTarget = match button {
+        GamepadButton::A => KeyTarget::Button(SdlButton::B),
+        GamepadButton::B => KeyTarget::Button(SdlButton::A),
+        GamepadButton::X => KeyTarget::Button(SdlButton::Y),
+        GamepadButton::Y => KeyTarget::Button(SdlButton::X),
+        GamepadButton::LST => KeyTarget::Button(SdlButton::LeftStick),
+        GamepadButton::RST => KeyTarget::Button(SdlButton::RightStick),
+        GamepadButton::L => KeyTarget::Button(SdlButton::LeftShoulder),
+        GamepadButton::R => KeyTarget::Button(SdlButton::RightShoulder),
+        GamepadButton::ZL => KeyTarget::Trigger(SdlAxis::TriggerLeft),
+        GamepadButton::ZR => KeyTarget::Trigger(SdlAxis::TriggerRight),
+        GamepadButton::Plus => KeyTarget::Button(SdlButton::Start),
+        GamepadButton::Minus => KeyTarget::Button(SdlButton::Back),
+        GamepadButton::DL => KeyTarget::Button(SdlButton::DPadLeft),
+        GamepadButton::DU => KeyTarget::Button(SdlButton::DPadUp),
+        GamepadButton::DR => KeyTarget::Button(SdlButton::DPadRight),
+        GamepadButton::DD => KeyTarget::Button(SdlButton::DPadDown),
+        GamepadButton::LL => KeyTarget::Stick(SdlAxis::LeftX, false),
+        GamepadButton::LU => KeyTarget::Stick(SdlAxis::LeftY, false),
+        GamepadButton::LR => KeyTarget::Stick(SdlAxis::LeftX, true),
+        GamepadButton::LD => KeyTarget::Stick(SdlAxis::LeftY, true),
+        GamepadButton::RL => KeyTarget::Stick(SdlAxis::RightX, false),
+        GamepadButton::RU => KeyTarget::Stick(SdlAxis::RightY, false),
+        GamepadButton::RR => KeyTarget::Stick(SdlAxis::RightX, true),
+        GamepadButton::RD => KeyTarget::Stick(SdlAxis::RightY, true),
+        GamepadButton::SLL => KeyTarget::Button(SdlButton::Paddle1),
+        GamepadButton::SRL => KeyTarget::Button(SdlButton::Paddle2),
+        GamepadButton::SLR => KeyTarget::Button(SdlButton::Paddle3),
+        GamepadButton::SRR => KeyTarget::Button(SdlButton::Paddle4),
+        GamepadButton::Home => KeyTarget::Button(SdlButton::Guide),
+        GamepadButton::Capture => KeyTarget::Button(SdlButton::Misc1)
+    };
+    return target;
+}
+
+/// Returns the map that turns every input from to_target() back into its
+/// button. Anything that isn't one of those doesn't press anything.
+fn get_button_map() -> ButtonMap {
+    let mut button_map: ButtonMap = ButtonMap::default();
+    for physical in PhysicalButton::ALL {
+        button_map.set(physical, &[]);
+    }
+    for button in GamepadButton::ALL {
+        let physical: PhysicalButton = match to_target(button) {
+            KeyTarget::Button(sdl) => PhysicalButton::from_sdl(sdl),
+            KeyTarget::Trigger(SdlAxis::TriggerLeft)
+                => PhysicalButton::TriggerLeft,
+            KeyTarget::Trigger(_) => PhysicalButton::TriggerRight,
+            // Sticks are moved rather than mapped.
+            KeyTarget::Stick(_, _) => continue
+        };
+        button_map.set(physical, &[button]);
+    }
+    return button_map;
+}
+
+/// The stick axes, in the order that KeyboardMouseState keeps them in.
+const STICK_AXES: [SdlAxis; 4] = [
+    SdlAxis::LeftX, SdlAxis::LeftY, SdlAxis::RightX, SdlAxis::RightY
+];
+
+fn to_axis_index(axis: SdlAxis) -> usize {
+    match axis {
+        SdlAxis::LeftX => 0,
+        SdlAxis::LeftY => 1,
+        SdlAxis::RightX => 2,
+        _ => 3
+    }
+}
+
+/// Everything that the device threads share with the thread that moves the
+/// sticks.
+struct KeyboardMouseState {
+    // How many keys are holding each stick axis in each direction.
+    held: [(u32, u32); 4],
+    // How far the mouse has moved since the last update.
+    delta: (f32, f32),
+    // How far the mouse is pushing its stick, between -1 and 1.
+    mouse: (f32, f32),
+    // The last position sent for each stick axis.
+    sent: [i16; 4]
+}
+
+impl KeyboardMouseState {
+    fn new() -> KeyboardMouseState {
+        return KeyboardMouseState {
+            held: [(0, 0); 4],
+            delta: (0.0, 0.0),
+            mouse: (0.0, 0.0),
+            sent: [0; 4]
+        }
+    }
+
+    /// Moves the mouse's push on its stick along, then returns the axes that
+    /// moved since the last update.
+    fn update(&mut self, config: &KeyboardMouseConfig) -> Vec<(SdlAxis, i16)> {
+        self.mouse = (
+            (self.mouse.0 * config.decay + self.delta.0 * config.sensitivity)
+                .clamp(-1.0, 1.0),
+            (self.mouse.1 * config.decay + self.delta.1 * config.sensitivity)
+                .clamp(-1.0, 1.0)
+        );
+        self.delta = (0.0, 0.0);
+
+        let mouse_axes: (usize, usize) = match config.mouse_stick {
+            StickSide::Left => (0, 1),
+            StickSide::Right => (2, 3)
+        };
+        let mut moved: Vec<(SdlAxis, i16)> = vec!();
+        for (k, axis) in STICK_AXES.iter().enumerate() {
+            let (negative, positive): (u32, u32) = self.held[k];
+            let mut position: f32 = 0.0;
+            if positive > 0 {
+                position += 1.0;
+            }
+            if negative > 0 {
+                position -= 1.0;
+            }
+            if k == mouse_axes.0 {
+                position += self.mouse.0;
+            } else if k == mouse_axes.1 {
+                position += self.mouse.1;
+            }
+            let value: i16 = (position.clamp(-1.0, 1.0) * i16::MAX as f32)
+                .round() as i16;
+            if value != self.sent[k] {
+                self.sent[k] = value;
+                moved.push((*axis, value));
+            }
+        }
+        return moved;
+    }
+}
+
+/// Reads keyboards and mice through evdev, and turns them into a gamepad.
+///
+/// Keys are turned into SDL input, so everything after this treats it like any
+/// other gamepad, except that it comes with a button map of its own.
+pub struct KeyboardMouseSource {
+    config: KeyboardMouseConfig,
+    ticks: Receiver<Instant>,
+    directory: PathBuf,
+    done: Arc<AtomicBool>
+}
+
+impl KeyboardMouseSource {
+    pub fn new(
+        config: KeyboardMouseConfig, ticks: Receiver<Instant>, directory: &Path,
+        done: Arc<AtomicBool>
+    ) -> KeyboardMouseSource {
+        return KeyboardMouseSource {
+            config: config,
+            ticks: ticks,
+            directory: directory.to_path_buf(),
+            done: done
+        }
+    }
+
+    fn get_targets(&self) -> Result<HashMap<Key, KeyTarget>, String> {
+        let mut targets: HashMap<Key, KeyTarget> = HashMap::new();
+        for (name, button) in self.config.keys.iter() {
+            let key: Key = Key::from_str(name)
+                .map_err(|_| format!("Unknown key \"{}\".", name))?;
+            targets.insert(key, to_target(*button));
+        }
+        return Ok(targets);
+    }
+}
+
+/// Opens a keyboard or mouse on a thread of its own, unless it's already open
+/// or is something else entirely.
+fn open_device(
+    path: PathBuf, input: &InputBuffer, targets: &Arc<HashMap<Key, KeyTarget>>,
+    state_mtx: &Arc<Mutex<KeyboardMouseState>>, grab: bool,
+    opened_mtx: &Arc<Mutex<HashSet<PathBuf>>>, done: &Arc<AtomicBool>
+) -> () {
+    if let Ok(mut opened) = opened_mtx.lock() {
+        if opened.contains(&path) {
+            return;
+        }
+        let mut device: Device = match Device::open(&path) {
+            Err(_) => return,
+            Ok(device) => device
+        };
+        let is_mouse: bool = device.supported_relative_axes()
+            .map_or(false, |axes| axes.contains(RelativeAxisType::REL_X));
+        let (is_keyboard, is_gamepad): (bool, bool) = match device
+            .supported_keys() {
+            None => (false, false),
+            Some(keys) => (
+                targets.keys().any(|key| keys.contains(*key)),
+                keys.contains(Key::BTN_SOUTH)
+            )
+        };
+        // Gamepads are left to the evdev source.
+        if is_gamepad || !(is_keyboard || is_mouse) {
+            return;
+        }
+        if grab {
+            if let Err(e) = device.grab() {
+                eprintln!("Failed to grab {}: {}", path.display(), e);
+            }
+        }
+        opened.insert(path.clone());
+
+        let device_input: InputBuffer = input.clone();
+        let device_targets: Arc<HashMap<Key, KeyTarget>> = Arc::clone(targets);
+        let device_state_mtx: Arc<Mutex<KeyboardMouseState>>
+            = Arc::clone(state_mtx);
+        let device_opened_mtx: Arc<Mutex<HashSet<PathBuf>>>
+            = Arc::clone(opened_mtx);
+        let device_done: Arc<AtomicBool> = Arc::clone(done);
+        thread::spawn(move || {
+            while !device_done.load(Ordering::Relaxed) {
+                let events: Vec<InputEvent> = match device.fetch_events() {
+                    Err(_) => break,
+                    Ok(events) => events.collect()
+                };
+                for event in events {
+                    let result: Result<(), String> = read_event(
+                        event, &device_input, &device_targets,
+                        &device_state_mtx);
+                    if let Err(e) = result {
+                        eprintln!("{}", e);
+                    }
+                }
+            }
+            if let Ok(mut opened) = device_opened_mtx.lock() {
+                opened.remove(&path);
+            }
+        });
+    }
+}
+
+fn read_event(
+    event: InputEvent, input: &InputBuffer,
+    targets: &HashMap<Key, KeyTarget>, state_mtx: &Mutex<KeyboardMouseState>
+) -> Result<(), String> {
+    match event.kind() {
+        // A value of 2 is the key repeating, which we don't care about.
+        InputEventKind::Key(key) if event.value() != 2 => {
+            let pressed: bool = event.value() == 1;
+            match targets.get(&key) {
+                None => Ok(()),
+                Some(KeyTarget::Button(button))
+                    => input.push_event(SdlEvent::ButtonPress {
+                        timestamp: 0,
+                        which: WHICH,
+                        button: *button,
+                        pressed: pressed
+                    }),
+                Some(KeyTarget::Trigger(axis))
+                    => input.push_event(SdlEvent::AxisMotion {
+                        timestamp: 0,
+                        which: WHICH,
+                        axis: *axis,
+                        value: if pressed { i16::MAX } else { 0 }
+                    }),
+                // Sticks are only moved on the next update, alongside the
+                // mouse.
+                Some(KeyTarget::Stick(axis, positive)) => {
+                    if let Ok(mut state) = state_mtx.lock() {
+                        let held: &mut (u32, u32)
+                            = &mut state.held[to_axis_index(*axis)];
+                        let count: &mut u32
+                            = if *positive { &mut held.1 } else { &mut held.0 };
+                        if pressed {
+                            *count += 1;
+                        } else {
+                            *count = count.saturating_sub(1);
+                        }
+                        return Ok(());
+                    } else {
+                        return Err(String::from("Failed to lock state."));
+                    }
+                }
+            }
+        },
+        InputEventKind::RelAxis(axis) => {
+            if let Ok(mut state) = state_mtx.lock() {
+                match axis {
+                    RelativeAxisType::REL_X
+                        => state.delta.0 += event.value() as f32,
+                    RelativeAxisType::REL_Y
+                        => state.delta.1 += event.value() as f32,
+                    _ => ()
+                }
+                return Ok(());
+            } else {
+                return Err(String::from("Failed to lock state."));
+            }
+        },
+        _ => Ok(())
+    }
+}
+
+impl InputSource for KeyboardMouseSource {
+    fn start(&mut self, input: InputBuffer) -> Result<(), String> {
+        let targets: Arc<HashMap<Key, KeyTarget>>
+            = Arc::new(self.get_targets()?);
+        let state_mtx: Arc<Mutex<KeyboardMouseState>>
+            = Arc::new(Mutex::new(KeyboardMouseState::new()));
+
+        input.push_device(WHICH, DeviceInfo {
+            guid: String::new(),
+            name: String::from("Keyboard and mouse"),
+            buttons: Some(get_button_map())
+        })?;
+        input.push_event(SdlEvent::ControllerAdded {
+            timestamp: 0,
+            which: WHICH
+        })?;
+
+        let open_input: InputBuffer = input.clone();
+        let open_state_mtx: Arc<Mutex<KeyboardMouseState>>
+            = Arc::clone(&state_mtx);
+        let opened_mtx: Arc<Mutex<HashSet<PathBuf>>>
+            = Arc::new(Mutex::new(HashSet::new()));
+        let open_done: Arc<AtomicBool> = Arc::clone(&self.done);
+        let grab: bool = self.config.grab;
+        watch_devices(
+            &self.directory,
+            Arc::clone(&self.done),
+            move |path| open_device(
+                path, &open_input, &targets, &open_state_mtx, grab,
+                &opened_mtx, &open_done))?;
+
+        let config: KeyboardMouseConfig = self.config.clone();
+        let ticks: Receiver<Instant> = self.ticks.clone();
+        let done: Arc<AtomicBool> = Arc::clone(&self.done);
+        thread::spawn(move || {
+            while !done.load(Ordering::Relaxed) {
+                select! {
+                    recv(ticks) -> _ => {
+                        let moved: Vec<(SdlAxis, i16)>
+                            = match state_mtx.lock() {
+                                Err(_) => break,
+                                Ok(mut state) => state.update(&config)
+                            };
+                        for (axis, value) in moved {
+                            let result: Result<(), String> = input.push_event(
+                                SdlEvent::AxisMotion {
+                                    timestamp: 0,
+                                    which: WHICH,
+                                    axis: axis,
+                                    value: value
+                                });
+                            if let Err(e) = result {
+                                eprintln!("{}", e);
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        return Ok(());
+    }
+
+    fn stop(&mut self) -> Result<(), String> {
+        // Like with the evdev source, every thread stops once we're done.
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::input::gamepad::{Gamepad, GamepadType};
+
+    use super::*;
+
+    #[test]
+    fn keys_press_their_buttons_however_the_gamepad_is_mapped() {
+        let mut gamepad: Gamepad = Gamepad::new();
+        gamepad.connect(GamepadType::SwitchProController);
+        gamepad.set_gamepad_type(GamepadType::SidewaysRightJoyCon);
+        let mut remapped: ButtonMap = gamepad.get_button_map();
+        remapped.set(PhysicalButton::B, &[GamepadButton::Y]);
+        gamepad.set_button_map(remapped);
+        gamepad.set_device_buttons(Some(get_button_map()));
+
+        for button in GamepadButton::ALL {
+            let event = |pressed: bool| match to_target(button) {
+                KeyTarget::Button(sdl) => Some(SdlEvent::ButtonPress {
+                    timestamp: 0,
+                    which: WHICH,
+                    button: sdl,
+                    pressed: pressed
+                }),
+                KeyTarget::Trigger(axis) => Some(SdlEvent::AxisMotion {
+                    timestamp: 0,
+                    which: WHICH,
+                    axis: axis,
+                    value: if pressed { i16::MAX } else { 0 }
+                }),
+                KeyTarget::Stick(_, _) => None
+            };
+            if let Some(press) = event(true) {
+                gamepad.update(press).unwrap();
+                assert!(gamepad.get_pressed() == vec!(button));
+                gamepad.update(event(false).unwrap()).unwrap();
+                assert_eq!(gamepad.get_buttons(), 0);
+            }
+        }
+    }
+}
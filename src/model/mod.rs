@@ -1,4 +1,16 @@
+pub mod application_error;
+pub mod application_event;
 pub mod application_model;
+#[cfg(target_os = "linux")]
+pub mod evdev_interface;
 pub mod input;
+pub mod input_source;
+pub mod keyboard_mouse;
+#[cfg(target_os = "linux")]
+pub mod keyboard_mouse_interface;
+pub mod profile;
+pub mod remote_client;
+pub mod remote_interface;
+pub mod script_store;
 pub mod sdl_interface;
 pub mod sysmodule_interface;
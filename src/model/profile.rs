@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf}
+};
+
+use crate::model::{
+    application_error::{ApplicationError, ErrorCode},
+    input::gamepad::GamepadSettings
+};
+use serde::{Serialize, Deserialize};
+use toml::{value::Table, Value};
+
+/// The table in the config file that profiles are kept under.
+const PROFILES_KEY: &str = "profiles";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+/// A named set of settings that can be saved and switched between, like one
+/// for speedrunning and another for couch co-op.
+pub struct Profile {
+    pub ips: Vec<String>,
+    pub anarchy_mode: bool,
+    /// The settings of each gamepad slot, in order.
+    pub gamepads: Vec<GamepadSettings>,
+    /// The settings of each physical controller, by its GUID or name.
+    pub devices: HashMap<String, GamepadSettings>
+}
+
+impl Default for Profile {
+    fn default() -> Profile {
+        return Profile {
+            ips: vec!(),
+            anarchy_mode: false,
+            gamepads: vec!(),
+            devices: HashMap::new()
+        }
+    }
+}
+
+pub fn config_error(message: String) -> ApplicationError {
+    return ApplicationError::new(ErrorCode::ConfigError, &message);
+}
+
+/// Reads a whole config file, treating a missing one as empty.
+pub fn read_config(path: &Path) -> Result<Table, ApplicationError> {
+    match fs::read_to_string(path) {
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Table::new()),
+        Err(e) => Err(config_error(
+            format!("Failed to read config file: {}", e))),
+        Ok(contents) => toml::from_str(&contents).map_err(|e| config_error(
+            format!("Failed to parse config file: {}", e)))
+    }
+}
+
+pub fn write_config(path: &Path, table: Table) -> Result<(), ApplicationError> {
+    return fs::write(path, Value::Table(table).to_string())
+        .map_err(|e| config_error(
+            format!("Failed to write config file: {}", e)));
+}
+
+/// Reads and writes profiles in the `[profiles]` table of a TOML config file.
+///
+/// The file is read fresh every time, and only the profile being saved gets
+/// touched, so anything else in it (like the options) is left as it was.
+pub struct ProfileStore {
+    path: PathBuf
+}
+
+impl ProfileStore {
+    pub fn new(path: &Path) -> ProfileStore {
+        return ProfileStore {
+            path: path.to_path_buf()
+        }
+    }
+
+    fn read_profiles(&self) -> Result<Table, ApplicationError> {
+        match read_config(&self.path)?.remove(PROFILES_KEY) {
+            None => Ok(Table::new()),
+            Some(Value::Table(profiles)) => Ok(profiles),
+            Some(_) => Err(config_error(String::from(
+                "The profiles in the config file must be a table.")))
+        }
+    }
+
+    pub fn list(&self) -> Result<Vec<String>, ApplicationError> {
+        return Ok(self.read_profiles()?.keys().cloned().collect());
+    }
+
+    pub fn load(&self, name: &str) -> Result<Profile, ApplicationError> {
+        match self.read_profiles()?.remove(name) {
+            None => Err(ApplicationError::new(
+                ErrorCode::UnknownProfile,
+                &format!("There's no profile named \"{}\".", name))),
+            Some(value) => value.try_into().map_err(|e| config_error(
+                format!("Failed to parse profile \"{}\": {}", name, e)))
+        }
+    }
+
+    pub fn save(
+        &self, name: &str, profile: &Profile
+    ) -> Result<(), ApplicationError> {
+        let mut table: Table = read_config(&self.path)?;
+        let mut profiles: Table = match table.remove(PROFILES_KEY) {
+            None => Table::new(),
+            Some(Value::Table(profiles)) => profiles,
+            Some(_) => return Err(config_error(String::from(
+                "The profiles in the config file must be a table.")))
+        };
+        let value: Value = Value::try_from(profile).map_err(|e| config_error(
+            format!("Failed to serialize profile: {}", e)))?;
+        profiles.insert(String::from(name), value);
+        table.insert(String::from(PROFILES_KEY), Value::Table(profiles));
+        return write_config(&self.path, table);
+    }
+}
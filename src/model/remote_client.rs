@@ -0,0 +1,348 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, ErrorKind, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering}
+    },
+    thread,
+    time::{Duration, Instant}
+};
+
+use crate::model::{
+    application_event::ApplicationEvent,
+    input::device_info::DeviceInfo,
+    input_source::{InputBuffer, InputSource},
+    remote_interface::{RemoteMessage, RemoteReply}
+};
+use crossbeam_channel::{select, unbounded, Receiver, Sender};
+use sdl_event_server::SdlEvent;
+
+/// How long to wait for the other companion to let us in.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+/// How long to wait between tries at connecting.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+/// How long we can go without sending anything before we ping, which has to be
+/// well under how long the other companion waits before giving up on us.
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone)]
+/// Where to send our controllers to, and how.
+pub struct RemoteClientConfig {
+    pub address: String,
+    pub udp: bool,
+    pub secret: Option<String>
+}
+
+/// A connection to another companion, for a single controller.
+enum Connection {
+    Tcp(TcpStream),
+    Udp(UdpSocket)
+}
+
+fn to_json(message: &RemoteMessage) -> Result<String, String> {
+    return serde_json::to_string(message)
+        .map_err(|_| String::from("Failed to serialize message."));
+}
+
+fn read_reply(line: &str) -> Result<(), String> {
+    return match serde_json::from_str(line.trim()) {
+        Err(_) => Err(String::from("Got a reply that made no sense.")),
+        Ok(RemoteReply::Rejected { message }) => Err(message),
+        Ok(RemoteReply::Accepted) => Ok(())
+    };
+}
+
+impl Connection {
+    /// Connects and says hello, returning once we've been let in.
+    fn open(config: &RemoteClientConfig) -> Result<Connection, String> {
+        let address: SocketAddr = config.address.to_socket_addrs()
+            .map_err(|e| format!("Failed to resolve address: {}", e))?
+            .next()
+            .ok_or(String::from("Address didn't resolve to anything."))?;
+        let hello: RemoteMessage = RemoteMessage::Hello {
+            secret: config.secret.clone()
+        };
+
+        if config.udp {
+            let local: &str = if address.is_ipv4() {
+                "0.0.0.0:0"
+            } else {
+                "[::]:0"
+            };
+            let socket: UdpSocket = UdpSocket::bind(local)
+                .map_err(|e| format!("Failed to bind UDP: {}", e))?;
+            socket.connect(address)
+                .map_err(|e| format!("Failed to connect: {}", e))?;
+            socket.set_read_timeout(Some(CONNECT_TIMEOUT))
+                .map_err(|e| format!("Failed to set UDP timeout: {}", e))?;
+            socket.send(to_json(&hello)?.as_bytes())
+                .map_err(|e| format!("Failed to say hello: {}", e))?;
+            let mut buffer: [u8; 1024] = [0; 1024];
+            let length: usize = socket.recv(&mut buffer)
+                .map_err(|_| String::from("Nobody answered."))?;
+            read_reply(&String::from_utf8_lossy(&buffer[..length]))?;
+            // From here on, we only check for replies in passing.
+            socket.set_nonblocking(true)
+                .map_err(|e| format!("Failed to set UDP non-blocking: {}", e))?;
+            return Ok(Connection::Udp(socket));
+        } else {
+            let mut stream: TcpStream
+                = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)
+                    .map_err(|e| format!("Failed to connect: {}", e))?;
+            stream.set_nodelay(true).unwrap_or(());
+            stream.set_read_timeout(Some(CONNECT_TIMEOUT))
+                .map_err(|e| format!("Failed to set TCP timeout: {}", e))?;
+            let mut line: String = to_json(&hello)?;
+            line.push('\n');
+            stream.write_all(line.as_bytes())
+                .map_err(|e| format!("Failed to say hello: {}", e))?;
+            let mut reply: String = String::new();
+            let mut reader: BufReader<&TcpStream> = BufReader::new(&stream);
+            match reader.read_line(&mut reply) {
+                Err(_) | Ok(0) => return Err(String::from("Nobody answered.")),
+                Ok(_) => read_reply(&reply)?
+            }
+            return Ok(Connection::Tcp(stream));
+        }
+    }
+
+    fn send(&mut self, message: &RemoteMessage) -> Result<(), String> {
+        let json: String = to_json(message)?;
+        let result: std::io::Result<()> = match self {
+            Connection::Tcp(stream) => stream.write_all(
+                format!("{}\n", json).as_bytes()),
+            Connection::Udp(socket) => socket.send(json.as_bytes()).map(|_| ())
+        };
+        return result.map_err(|e| format!("Failed to send: {}", e));
+    }
+
+    /// Whether we've been told to go away since we were let in, or the other
+    /// companion is gone. Only UDP needs this, since a TCP connection just
+    /// fails to write instead.
+    fn was_rejected(&self) -> bool {
+        if let Connection::Udp(socket) = self {
+            let mut buffer: [u8; 1024] = [0; 1024];
+            loop {
+                match socket.recv(&mut buffer) {
+                    Err(e) => return e.kind() == ErrorKind::ConnectionRefused,
+                    Ok(length) => {
+                        let reply: String = String::from_utf8_lossy(
+                            &buffer[..length]).to_string();
+                        if let Err(_) = read_reply(&reply) {
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+        return false;
+    }
+}
+
+/// Remembers the latest event for every axis and button, so they can be sent
+/// again after we reconnect.
+fn remember(held: &mut Vec<SdlEvent>, event: SdlEvent) -> () {
+    held.retain(|old| match (old, &event) {
+        (
+            SdlEvent::AxisMotion { timestamp: _, which: _, axis: a, value: _ },
+            SdlEvent::AxisMotion { timestamp: _, which: _, axis: b, value: _ }
+        ) => *a as usize != *b as usize,
+        (
+            SdlEvent::ButtonPress {
+                timestamp: _, which: _, button: a, pressed: _
+            },
+            SdlEvent::ButtonPress {
+                timestamp: _, which: _, button: b, pressed: _
+            }
+        ) => a != b,
+        _ => true
+    });
+    held.push(event);
+}
+
+/// Sends everything a single controller does to the other companion, until
+/// the controller is removed.
+///
+/// Each controller gets a connection of its own, so the other companion gives
+/// it a gamepad of its own. If the connection drops, we keep trying to get it
+/// back, then send whatever's held down so nothing gets stuck.
+fn spawn_forwarder(
+    name: String, config: RemoteClientConfig, events: Receiver<SdlEvent>,
+    done: Arc<AtomicBool>
+) -> () {
+    thread::spawn(move || {
+        let mut connection: Option<Connection> = None;
+        let mut held: Vec<SdlEvent> = vec!();
+        let mut sequence: u64 = 0;
+        let mut last_try: Option<Instant> = None;
+        let mut last_sent: Instant = Instant::now();
+        let mut removed: bool = false;
+        while !done.load(Ordering::Relaxed) && !removed {
+            let mut outgoing: Vec<SdlEvent> = vec!();
+            select! {
+                recv(events) -> maybe_event => match maybe_event {
+                    Err(_) => removed = true,
+                    Ok(event) => {
+                        remember(&mut held, event);
+                        outgoing.push(event);
+                    }
+                },
+                default(PING_INTERVAL) => ()
+            }
+
+            if connection.is_none() && last_try.map_or(
+                true, |last_try| last_try.elapsed() >= RECONNECT_INTERVAL
+            ) && !removed {
+                last_try = Some(Instant::now());
+                match Connection::open(&config) {
+                    Err(e) => eprintln!(
+                        "Failed to connect {} to {}: {}",
+                        name, config.address, e),
+                    Ok(opened) => {
+                        eprintln!("Connected {} to {}.", name, config.address);
+                        connection = Some(opened);
+                        outgoing = held.clone();
+                    }
+                }
+            }
+
+            if let Some(opened) = &mut connection {
+                let mut result: Result<(), String> = Ok(());
+                for event in outgoing {
+                    sequence += 1;
+                    result = result.and_then(|_| opened.send(
+                        &RemoteMessage::Sdl {
+                            event: event,
+                            sequence: Some(sequence)
+                        }));
+                    last_sent = Instant::now();
+                }
+                if result.is_ok() && last_sent.elapsed() >= PING_INTERVAL {
+                    result = opened.send(&RemoteMessage::Ping);
+                    last_sent = Instant::now();
+                }
+                if result.is_ok() && opened.was_rejected() {
+                    result = Err(
+                        String::from("The other companion forgot us."));
+                }
+                if let Err(e) = result {
+                    eprintln!("Lost connection for {}: {}", name, e);
+                    connection = None;
+                }
+            }
+        }
+        if let Some(mut opened) = connection {
+            opened.send(&RemoteMessage::Goodbye).unwrap_or(());
+        }
+    });
+}
+
+/// Hands every controller's events to its own forwarder.
+struct Forwarders {
+    senders: HashMap<u32, Sender<SdlEvent>>,
+    devices: HashMap<u32, DeviceInfo>,
+    config: RemoteClientConfig,
+    done: Arc<AtomicBool>
+}
+
+impl Forwarders {
+    fn forward(&mut self, event: SdlEvent) -> () {
+        match event {
+            SdlEvent::ControllerAdded { timestamp: _, which } => {
+                let name: String = match self.devices.get(&which) {
+                    Some(device) if device.name.len() != 0
+                        => device.name.clone(),
+                    _ => format!("controller {}", which)
+                };
+                let (tx, rx): (Sender<SdlEvent>, Receiver<SdlEvent>)
+                    = unbounded();
+                spawn_forwarder(
+                    name, self.config.clone(), rx, Arc::clone(&self.done));
+                self.senders.insert(which, tx);
+            },
+            SdlEvent::ControllerRemoved { timestamp: _, which } => {
+                // Dropping its sender is what tells the forwarder to say
+                // goodbye.
+                self.senders.remove(&which);
+                self.devices.remove(&which);
+            },
+            SdlEvent::AxisMotion { timestamp: _, which, axis: _, value: _ }
+            | SdlEvent::ButtonPress {
+                timestamp: _, which, button: _, pressed: _
+            } => if let Some(tx) = self.senders.get(&which) {
+                tx.send(event).unwrap_or(());
+            }
+        }
+    }
+}
+
+/// Reads local controllers and streams them to another companion, instead of
+/// to a sysmodule.
+pub struct RemoteClientModel {
+    sources: Vec<Box<dyn InputSource>>,
+    events_rx: Receiver<ApplicationEvent>
+}
+
+impl RemoteClientModel {
+    pub fn new(
+        ticks: Receiver<Instant>, done: Arc<AtomicBool>,
+        mut sources: Vec<Box<dyn InputSource>>, config: RemoteClientConfig
+    ) -> Result<RemoteClientModel, String> {
+        let (events_tx, events_rx): (
+            Sender<ApplicationEvent>, Receiver<ApplicationEvent>
+        ) = unbounded();
+        let input: InputBuffer = InputBuffer::new(events_tx);
+        for k in 0..sources.len() {
+            if let Err(e) = sources[k].start(input.for_source(k)) {
+                // The ones that did start are already reading, so they have to
+                // be stopped again.
+                done.store(true, Ordering::SeqCst);
+                for source in sources[..k].iter_mut() {
+                    source.stop().unwrap_or(());
+                }
+                return Err(format!("Failed to start input source: {}", e));
+            }
+        }
+
+        thread::spawn(move || {
+            let mut forwarders: Forwarders = Forwarders {
+                senders: HashMap::new(),
+                devices: HashMap::new(),
+                config: config,
+                done: Arc::clone(&done)
+            };
+            while !done.load(Ordering::Relaxed) {
+                select! {
+                    recv(ticks) -> _ => {
+                        let (events, devices): (
+                            Vec<SdlEvent>, Vec<(u32, DeviceInfo)>
+                        ) = input.take();
+                        forwarders.devices.extend(devices);
+                        for event in events {
+                            forwarders.forward(event);
+                        }
+                    }
+                }
+            }
+        });
+
+        return Ok(RemoteClientModel {
+            sources: sources,
+            events_rx: events_rx
+        });
+    }
+
+    /// Takes any events that the sources reported, like the server exiting.
+    pub fn take_events(&self) -> Vec<ApplicationEvent> {
+        return self.events_rx.try_iter().collect();
+    }
+
+    pub fn exit(&mut self) -> Result<(), String> {
+        for source in self.sources.iter_mut() {
+            source.stop()?;
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,524 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream, UdpSocket},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU32, Ordering}
+    },
+    thread,
+    time::{Duration, Instant}
+};
+
+use crate::model::{
+    input::{device_info::DeviceInfo, script_event::ScriptEvent},
+    input_source::{InputBuffer, InputSource}
+};
+use crossbeam_channel::{select, Receiver};
+use sdl_event_server::SdlEvent;
+use serde::{Serialize, Deserialize};
+
+/// How long a UDP client can go without sending anything before we assume it's
+/// gone. Clients that have nothing to send should ping.
+const UDP_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often the UDP thread stops waiting to check for clients that timed out.
+const UDP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long a TCP client gets to say hello before it's dropped.
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+/// How many events a single client can have held back by its waits at once.
+/// Scripts that would go over this are dropped whole.
+const MAX_PENDING_EVENTS: usize = 10_000;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+/// Where to listen for remote players, and what they need to know to join.
+pub struct RemoteConfig {
+    pub tcp: Option<String>,
+    pub udp: Option<String>,
+    /// If set, clients have to say this in their hello to be let in.
+    pub secret: Option<String>
+}
+
+impl Default for RemoteConfig {
+    fn default() -> RemoteConfig {
+        return RemoteConfig {
+            tcp: None,
+            udp: None,
+            secret: None
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+/// Messages that remote players send us, as JSON.
+///
+/// Over TCP, these are newline-delimited, and over UDP, every datagram is its
+/// own message. Every client has to start with a hello, after which it gets a
+/// gamepad slot of its own, just like a local controller would.
+pub enum RemoteMessage {
+    Hello { secret: Option<String> },
+    // The "which" of these is ignored, and the controller being added or
+    // removed is up to the client joining and leaving instead.
+    //
+    // If a sequence number is given, anything that arrives after something
+    // with a higher one is dropped, since UDP doesn't keep things in order.
+    Sdl {
+        event: SdlEvent,
+        #[serde(default)]
+        sequence: Option<u64>
+    },
+    // Waits hold back everything the client sends after them.
+    Script {
+        event: ScriptEvent,
+        #[serde(default)]
+        sequence: Option<u64>
+    },
+    // Keeps a UDP client from timing out when it has nothing else to send.
+    Ping,
+    Goodbye
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+/// What we reply to a remote player's hello with.
+///
+/// UDP clients that we don't know about get rejected too, so a client can tell
+/// that it has to say hello again, like after we restart.
+pub enum RemoteReply {
+    Accepted,
+    Rejected { message: String }
+}
+
+/// Events that are being held back by a wait, and when they can go through, by
+/// the controller they're for.
+type PendingMutex = Arc<Mutex<HashMap<u32, Vec<(Instant, SdlEvent)>>>>;
+
+/// Everything about a single remote player.
+struct RemoteClient {
+    which: u32,
+    // When everything this client sends can go through, after its waits.
+    resume_at: Instant,
+    last_seen: Instant,
+    last_sequence: Option<u64>
+}
+
+/// Everything that both the TCP and UDP threads need.
+#[derive(Clone)]
+struct RemoteShared {
+    input: InputBuffer,
+    pending_mtx: PendingMutex,
+    next_which: Arc<AtomicU32>,
+    secret: Option<String>,
+    frame: Duration
+}
+
+impl RemoteShared {
+    /// Lets a client in if it knows the secret, adding a controller for it.
+    fn join(
+        &self, secret: Option<String>, name: String
+    ) -> Result<RemoteClient, String> {
+        if let Some(expected) = &self.secret {
+            if secret.as_ref() != Some(expected) {
+                return Err(String::from("Wrong secret."));
+            }
+        }
+        let which: u32 = self.next_which.fetch_add(1, Ordering::Relaxed);
+        self.input.push_device(which, DeviceInfo {
+            guid: String::new(),
+            name: name,
+            buttons: None
+        })?;
+        self.input.push_event(SdlEvent::ControllerAdded {
+            timestamp: 0,
+            which: which
+        })?;
+        return Ok(RemoteClient {
+            which: which,
+            resume_at: Instant::now(),
+            last_seen: Instant::now(),
+            last_sequence: None
+        });
+    }
+
+    fn leave(&self, client: &RemoteClient) -> () {
+        // Whatever it was still waiting on would be for a controller that's
+        // gone by then.
+        if let Ok(mut pending) = self.pending_mtx.lock() {
+            pending.remove(&client.which);
+        }
+        self.input.push_event(SdlEvent::ControllerRemoved {
+            timestamp: 0,
+            which: client.which
+        }).unwrap_or(());
+    }
+
+    /// Whether something with this sequence number is older than what the
+    /// client already sent, remembering it if it isn't.
+    fn is_stale(client: &mut RemoteClient, sequence: Option<u64>) -> bool {
+        if let Some(sequence) = sequence {
+            if let Some(last) = client.last_sequence {
+                if sequence <= last {
+                    return true;
+                }
+            }
+            client.last_sequence = Some(sequence);
+        }
+        return false;
+    }
+
+    /// Handles anything a client sends after its hello. Returns false once the
+    /// client says goodbye.
+    fn receive(
+        &self, client: &mut RemoteClient, message: RemoteMessage
+    ) -> bool {
+        let now: Instant = Instant::now();
+        client.last_seen = now;
+        let event: SdlEvent = match message {
+            RemoteMessage::Hello { secret: _ } | RemoteMessage::Ping
+                => return true,
+            RemoteMessage::Goodbye => return false,
+            RemoteMessage::Sdl { event: _, sequence }
+            | RemoteMessage::Script { event: _, sequence }
+                if RemoteShared::is_stale(client, sequence) => return true,
+            RemoteMessage::Sdl { event, sequence: _ } => match event {
+                SdlEvent::AxisMotion { timestamp, which: _, axis, value }
+                    => SdlEvent::AxisMotion {
+                        timestamp: timestamp,
+                        which: client.which,
+                        axis: axis,
+                        value: value
+                    },
+                SdlEvent::ButtonPress { timestamp, which: _, button, pressed }
+                    => SdlEvent::ButtonPress {
+                        timestamp: timestamp,
+                        which: client.which,
+                        button: button,
+                        pressed: pressed
+                    },
+                _ => return true
+            },
+            RemoteMessage::Script { event, sequence: _ } => {
+                // Anything bigger than a single press or motion gets
+                // expanded, with every part of it held back until it's due.
+                let (expanded, length): (Vec<(ScriptEvent, usize)>, usize)
+                    = match ScriptEvent::expand(&[event], self.frame) {
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            return true;
+                        },
+                        Ok(expanded) => expanded
+                    };
+                if self.count_pending(client.which) + expanded.len()
+                    > MAX_PENDING_EVENTS {
+                    eprintln!(
+                        "Too many events are being held back for controller \
+                        {}.",
+                        client.which);
+                    return true;
+                }
+                let start: Instant = client.resume_at.max(now);
+                // Nothing in the script is due later than its end, so if that
+                // fits, so does everything else.
+                let end: Instant = match due_at(start, self.frame, length) {
+                    None => {
+                        eprintln!("Scripts can't wait that long.");
+                        return true;
+                    },
+                    Some(end) => end
+                };
+                for (part, delay) in expanded {
+                    if let Some(event) = part.to_sdl(client.which) {
+                        self.deliver(
+                            client.which,
+                            due_at(start, self.frame, delay).unwrap_or(end),
+                            event);
+                    }
+                }
+                client.resume_at = end;
+                return true;
+            }
+        };
+        self.deliver(client.which, client.resume_at, event);
+        return true;
+    }
+
+    fn count_pending(&self, which: u32) -> usize {
+        return match self.pending_mtx.lock() {
+            Err(_) => 0,
+            Ok(pending) => pending.get(&which).map_or(0, Vec::len)
+        };
+    }
+
+    /// Lets an event through once it's due, or holds it back until then, as
+    /// long as the controller doesn't already have too much held back.
+    fn deliver(&self, which: u32, due: Instant, event: SdlEvent) -> () {
+        if due <= Instant::now() {
+            if let Err(e) = self.input.push_event(event) {
+                eprintln!("{}", e);
+            }
+        } else if let Ok(mut pending) = self.pending_mtx.lock() {
+            let held: &mut Vec<(Instant, SdlEvent)>
+                = pending.entry(which).or_default();
+            if held.len() < MAX_PENDING_EVENTS {
+                held.push((due, event));
+            } else {
+                eprintln!(
+                    "Too many events are being held back for controller {}.",
+                    which);
+            }
+        }
+    }
+
+    /// Lets through every held back event whose wait is over.
+    fn flush(&self) -> () {
+        let now: Instant = Instant::now();
+        let due: Vec<SdlEvent> = match self.pending_mtx.lock() {
+            Err(_) => return,
+            Ok(mut pending) => {
+                let mut due: Vec<SdlEvent> = vec!();
+                for held in pending.values_mut() {
+                    held.retain(|(resume_at, event)| {
+                        if *resume_at <= now {
+                            due.push(*event);
+                            return false;
+                        }
+                        return true;
+                    });
+                }
+                pending.retain(|_, held| held.len() != 0);
+                due
+            }
+        };
+        for event in due {
+            if let Err(e) = self.input.push_event(event) {
+                eprintln!("{}", e);
+            }
+        }
+    }
+}
+
+/// When something that many frames after the start is due, unless it's so
+/// far off that it doesn't fit in an Instant.
+fn due_at(start: Instant, frame: Duration, frames: usize) -> Option<Instant> {
+    return u32::try_from(frames)
+        .ok()
+        .and_then(|frames| frame.checked_mul(frames))
+        .and_then(|wait| start.checked_add(wait));
+}
+
+fn to_line(reply: &RemoteReply) -> String {
+    let mut line: String = serde_json::to_string(reply).unwrap_or_default();
+    line.push('\n');
+    return line;
+}
+
+/// Reads from a single TCP client until it leaves.
+fn spawn_tcp_client(
+    stream: TcpStream, address: SocketAddr, shared: RemoteShared
+) -> () {
+    thread::spawn(move || {
+        let mut writer: TcpStream = match stream.try_clone() {
+            Err(_) => return,
+            Ok(writer) => writer
+        };
+        let mut reader: BufReader<TcpStream> = BufReader::new(stream);
+        let mut client: Option<RemoteClient> = None;
+        let mut buffer: String = String::new();
+        // Until it says hello, a client only gets so long to do it, no matter
+        // what else it sends.
+        let hello_by: Instant = Instant::now() + HELLO_TIMEOUT;
+        loop {
+            if client.is_none() {
+                let left: Duration
+                    = hello_by.saturating_duration_since(Instant::now());
+                if left.is_zero()
+                    || reader.get_ref().set_read_timeout(Some(left)).is_err() {
+                    break;
+                }
+            }
+            buffer.clear();
+            match reader.read_line(&mut buffer) {
+                Err(_) | Ok(0) => break,
+                Ok(_) => ()
+            }
+            let message: RemoteMessage = match serde_json::from_str(
+                buffer.trim()
+            ) {
+                Err(_) => continue,
+                Ok(message) => message
+            };
+            match (&mut client, message) {
+                (None, RemoteMessage::Hello { secret }) => {
+                    match shared.join(secret, address.to_string()) {
+                        Err(e) => {
+                            let reply: RemoteReply
+                                = RemoteReply::Rejected { message: e };
+                            writer.write_all(to_line(&reply).as_bytes())
+                                .unwrap_or(());
+                            break;
+                        },
+                        Ok(joined) => {
+                            client = Some(joined);
+                            let reply: String = to_line(&RemoteReply::Accepted);
+                            if let Err(_) = writer.write_all(reply.as_bytes()) {
+                                break;
+                            }
+                            if let Err(_) = reader.get_ref().set_read_timeout(
+                                None
+                            ) {
+                                break;
+                            }
+                        }
+                    }
+                },
+                // Nothing counts until the client says hello.
+                (None, _) => (),
+                (Some(joined), message) => if !shared.receive(joined, message) {
+                    break;
+                }
+            }
+        }
+        if let Some(joined) = client {
+            shared.leave(&joined);
+        }
+    });
+}
+
+/// Handles every UDP client, which are told apart by their addresses.
+fn spawn_udp(
+    socket: UdpSocket, shared: RemoteShared, done: Arc<AtomicBool>
+) -> () {
+    thread::spawn(move || {
+        let mut clients: HashMap<SocketAddr, RemoteClient> = HashMap::new();
+        let mut buffer: [u8; 4096] = [0; 4096];
+        while !done.load(Ordering::Relaxed) {
+            if let Ok((length, address)) = socket.recv_from(&mut buffer) {
+                let message: Option<RemoteMessage>
+                    = serde_json::from_slice(&buffer[..length]).ok();
+                match (clients.get_mut(&address), message) {
+                    (_, None) => (),
+                    (None, Some(RemoteMessage::Hello { secret })) => {
+                        let reply: RemoteReply = match shared.join(
+                            secret, address.to_string()
+                        ) {
+                            Err(e) => RemoteReply::Rejected { message: e },
+                            Ok(joined) => {
+                                clients.insert(address, joined);
+                                RemoteReply::Accepted
+                            }
+                        };
+                        socket.send_to(to_line(&reply).as_bytes(), address)
+                            .unwrap_or(0);
+                    },
+                    (None, Some(RemoteMessage::Goodbye)) => (),
+                    (None, Some(_)) => {
+                        let reply: RemoteReply = RemoteReply::Rejected {
+                            message: String::from("Say hello first.")
+                        };
+                        socket.send_to(to_line(&reply).as_bytes(), address)
+                            .unwrap_or(0);
+                    },
+                    (Some(client), Some(message)) => {
+                        if !shared.receive(client, message) {
+                            if let Some(client) = clients.remove(&address) {
+                                shared.leave(&client);
+                            }
+                        }
+                    }
+                }
+            }
+
+            let now: Instant = Instant::now();
+            clients.retain(|_, client| {
+                if now.duration_since(client.last_seen) > UDP_TIMEOUT {
+                    shared.leave(client);
+                    return false;
+                }
+                return true;
+            });
+        }
+    });
+}
+
+/// Takes input from remote players over the network, giving each of them a
+/// gamepad of their own.
+pub struct RemoteSource {
+    config: RemoteConfig,
+    ticks: Receiver<Instant>,
+    frame: Duration,
+    done: Arc<AtomicBool>
+}
+
+impl RemoteSource {
+    /// The frame is how long a single update takes, for when scripts wait.
+    pub fn new(
+        config: RemoteConfig, ticks: Receiver<Instant>, frame: Duration,
+        done: Arc<AtomicBool>
+    ) -> RemoteSource {
+        return RemoteSource {
+            config: config,
+            ticks: ticks,
+            frame: frame,
+            done: done
+        }
+    }
+}
+
+impl InputSource for RemoteSource {
+    fn start(&mut self, input: InputBuffer) -> Result<(), String> {
+        let shared: RemoteShared = RemoteShared {
+            input: input,
+            pending_mtx: Arc::new(Mutex::new(HashMap::new())),
+            next_which: Arc::new(AtomicU32::new(0)),
+            secret: self.config.secret.clone(),
+            frame: self.frame
+        };
+
+        if let Some(address) = &self.config.udp {
+            let socket: UdpSocket = UdpSocket::bind(address)
+                .map_err(|e| format!("Failed to bind remote UDP: {}", e))?;
+            socket.set_read_timeout(Some(UDP_POLL_INTERVAL))
+                .map_err(|e| format!("Failed to set UDP timeout: {}", e))?;
+            spawn_udp(socket, shared.clone(), Arc::clone(&self.done));
+        }
+        let listener: Option<TcpListener> = match &self.config.tcp {
+            None => None,
+            Some(address) => {
+                let listener: TcpListener = TcpListener::bind(address)
+                    .map_err(|e| format!("Failed to bind remote TCP: {}", e))?;
+                listener.set_nonblocking(true).map_err(|e| format!(
+                    "Failed to make remote TCP non-blocking: {}", e))?;
+                Some(listener)
+            }
+        };
+
+        // Accepting new TCP clients and letting waits finish both only need to
+        // happen once per tick.
+        let ticks: Receiver<Instant> = self.ticks.clone();
+        let done: Arc<AtomicBool> = Arc::clone(&self.done);
+        thread::spawn(move || {
+            while !done.load(Ordering::Relaxed) {
+                select! {
+                    recv(ticks) -> _ => {
+                        if let Some(listener) = &listener {
+                            while let Ok((stream, address))
+                                = listener.accept() {
+                                if let Ok(_) = stream.set_nonblocking(false) {
+                                    spawn_tcp_client(
+                                        stream, address, shared.clone());
+                                }
+                            }
+                        }
+                        shared.flush();
+                    }
+                }
+            }
+        });
+        return Ok(());
+    }
+
+    fn stop(&mut self) -> Result<(), String> {
+        // The TCP clients' threads block on reading, but they'll go away with
+        // us, and everything else checks if we're done.
+        return Ok(());
+    }
+}
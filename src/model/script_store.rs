@@ -0,0 +1,99 @@
+use std::path::{Path, PathBuf};
+
+use crate::model::{
+    application_error::{ApplicationError, ErrorCode},
+    input::script_event::ScriptEvent,
+    profile::{config_error, read_config, write_config}
+};
+use toml::{value::Table, Value};
+
+/// The table in the config file that scripts are kept under.
+const SCRIPTS_KEY: &str = "scripts";
+
+fn unknown_script(name: &str) -> ApplicationError {
+    return ApplicationError::new(
+        ErrorCode::UnknownScript,
+        &format!("There's no script named \"{}\".", name));
+}
+
+/// The TOML serializer can't write enums with fields, like script events, so
+/// scripts go through JSON first. The tables end up the same either way.
+fn to_toml(script: &Vec<ScriptEvent>) -> Result<Value, String> {
+    return serde_json::to_value(script)
+        .and_then(|json| serde_json::from_value(json))
+        .map_err(|e| e.to_string());
+}
+
+fn from_toml(value: Value) -> Result<Vec<ScriptEvent>, String> {
+    return serde_json::to_value(value)
+        .and_then(|json| serde_json::from_value(json))
+        .map_err(|e| e.to_string());
+}
+
+/// Reads and writes named scripts in the `[scripts]` table of a TOML config
+/// file, the same way profiles are.
+///
+/// Keeping them in the config file means anything else that reads it can use
+/// them too, without having to send them over.
+pub struct ScriptStore {
+    path: PathBuf
+}
+
+impl ScriptStore {
+    pub fn new(path: &Path) -> ScriptStore {
+        return ScriptStore {
+            path: path.to_path_buf()
+        }
+    }
+
+    /// Reads the whole config file, along with the scripts taken out of it.
+    fn read_scripts(&self) -> Result<(Table, Table), ApplicationError> {
+        let mut table: Table = read_config(&self.path)?;
+        match table.remove(SCRIPTS_KEY) {
+            None => Ok((table, Table::new())),
+            Some(Value::Table(scripts)) => Ok((table, scripts)),
+            Some(_) => Err(config_error(String::from(
+                "The scripts in the config file must be a table.")))
+        }
+    }
+
+    fn write_scripts(
+        &self, mut table: Table, scripts: Table
+    ) -> Result<(), ApplicationError> {
+        table.insert(String::from(SCRIPTS_KEY), Value::Table(scripts));
+        return write_config(&self.path, table);
+    }
+
+    pub fn list(&self) -> Result<Vec<String>, ApplicationError> {
+        return Ok(self.read_scripts()?.1.keys().cloned().collect());
+    }
+
+    pub fn load(
+        &self, name: &str
+    ) -> Result<Vec<ScriptEvent>, ApplicationError> {
+        match self.read_scripts()?.1.remove(name) {
+            None => Err(unknown_script(name)),
+            Some(value) => from_toml(value).map_err(|e| config_error(
+                format!("Failed to parse script \"{}\": {}", name, e)))
+        }
+    }
+
+    /// Saves a script, replacing whichever one had the same name.
+    pub fn save(
+        &self, name: &str, script: &Vec<ScriptEvent>
+    ) -> Result<(), ApplicationError> {
+        let (table, mut scripts): (Table, Table) = self.read_scripts()?;
+        let value: Value = to_toml(script).map_err(|e| config_error(
+            format!("Failed to serialize script: {}", e)))?;
+        scripts.insert(String::from(name), value);
+        return self.write_scripts(table, scripts);
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), ApplicationError> {
+        let (table, mut scripts): (Table, Table) = self.read_scripts()?;
+        if let None = scripts.remove(name) {
+            return Err(unknown_script(name));
+        }
+        return self.write_scripts(table, scripts);
+    }
+}
@@ -1,10 +1,43 @@
 use std::{
     io::{BufReader, BufRead, BufWriter, Write},
-    process::{Child, ChildStdin, ChildStdout},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering}
+    },
+    thread
 };
 
+use crate::model::{
+    application_event::ApplicationEvent,
+    input::device_info::DeviceInfo,
+    input_source::{InputBuffer, InputSource}
+};
 use sdl_event_server::SdlEvent;
-use serde_json;
+use serde_json::{self, Value};
+
+/// Picks the device's GUID and name out of a ControllerAdded line.
+///
+/// The server sends these as "guid" and "name" fields alongside "which", with
+/// the GUID formatted the way SDL_JoystickGetGUIDString() does. SdlEvent
+/// doesn't have room for them, so they're read separately, and anything an
+/// older server leaves out is left empty.
+fn read_device_info(line: &Value) -> Option<(u32, DeviceInfo)> {
+    let added: &Value = line.get("ControllerAdded")?;
+    let which: u32 = added.get("which")?.as_u64()? as u32;
+    let field = |key: &str| String::from(
+        added.get(key).and_then(Value::as_str).unwrap_or(""));
+    let device: DeviceInfo = DeviceInfo {
+        guid: field("guid"),
+        name: field("name"),
+        buttons: None
+    };
+    if device.get_key().len() == 0 {
+        return None;
+    }
+    return Some((which, device));
+}
 
 pub struct SdlReader {
     reader: BufReader<ChildStdout>
@@ -24,23 +57,33 @@ impl SdlReader {
         }
     }
 
-    pub fn read_into(
-        &mut self, events: &mut Vec<SdlEvent>
-    ) -> Result<(), String> {
+    /// Reads events until the server exits.
+    pub fn read_into(&mut self, input: &InputBuffer) -> Result<(), String> {
         let mut buffer: String = String::new();
         while self.has_data() {
             if let Err(_) = self.reader.read_line(&mut buffer) {
                 return Err(String::from("Failed to read from server buffer."));
             }
+            let line: Value = match serde_json::from_str(buffer.trim()) {
+                Err(_) => {
+                    buffer.clear();
+                    continue;
+                },
+                Ok(line) => line
+            };
+            if let Some((which, device)) = read_device_info(&line) {
+                input.push_device(which, device)?;
+            }
             let maybe_event: serde_json::Result<SdlEvent>
-                = serde_json::from_str(buffer.trim());
-            match maybe_event {
-                Err(_) => (),
-                // TODO: Double check that this pushes in the right order
-                Ok(event) => events.push(event)
+                = serde_json::from_value(line);
+            if let Ok(event) = maybe_event {
+                input.push_event(event)?;
             }
+            buffer.clear();
         }
-        return Ok(());
+        // fill_buf() blocks until the server writes something, so the only way
+        // we fall out of the loop is if the server's stdout was closed.
+        return Err(String::from("Server closed its stdout."));
     }
 }
 
@@ -70,3 +113,93 @@ impl SdlWriter {
         }
     }
 }
+
+/// The SDL event server, run as a child process that we read events from.
+pub struct SdlServerSource {
+    server_path: PathBuf,
+    done: Arc<AtomicBool>,
+    writer: Option<SdlWriter>
+}
+
+impl SdlServerSource {
+    /// Since nothing works without the server, we'll be done once it exits.
+    pub fn new(server_path: &Path, done: Arc<AtomicBool>) -> SdlServerSource {
+        return SdlServerSource {
+            server_path: server_path.to_path_buf(),
+            done: done,
+            writer: None
+        }
+    }
+}
+
+impl InputSource for SdlServerSource {
+    fn start(&mut self, input: InputBuffer) -> Result<(), String> {
+        let mut server: Child = Command::new(&self.server_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to execute SDL server: {}", e))?;
+        let server_stdout: ChildStdout = server.stdout
+            .take()
+            .ok_or(String::from("Failed to pipe server's stdout."))?;
+        let server_stdin: ChildStdin = server.stdin
+            .take()
+            .ok_or(String::from("Failed to pipe server's stdin."))?;
+
+        let mut reader: SdlReader = SdlReader::new(server_stdout);
+        self.writer = Some(SdlWriter::new(server, server_stdin));
+
+        let read_done: Arc<AtomicBool> = Arc::clone(&self.done);
+        // Reading from the server blocks until it writes something, so it gets
+        // its own thread.
+        thread::spawn(move || {
+            if let Err(_) = reader.read_into(&input) {
+                // If we're already done, then we're the ones who told the
+                // server to exit.
+                if !read_done.load(Ordering::Relaxed) {
+                    input.send(ApplicationEvent::ServerExited);
+                }
+                read_done.store(true, Ordering::SeqCst);
+            }
+        });
+        return Ok(());
+    }
+
+    fn stop(&mut self) -> Result<(), String> {
+        match &mut self.writer {
+            None => Ok(()),
+            Some(writer) => writer.exit()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_device_info_from_controller_added() {
+        let line: Value = serde_json::from_str(
+            r#"{"ControllerAdded":{"timestamp":5,"which":2,
+                "guid":"030000005e0400008e02000010010000",
+                "name":"Xbox 360 Controller"}}"#).unwrap();
+        let (which, device): (u32, DeviceInfo)
+            = read_device_info(&line).unwrap();
+        assert_eq!(which, 2);
+        assert_eq!(device.guid, "030000005e0400008e02000010010000");
+        assert_eq!(device.name, "Xbox 360 Controller");
+        // The event itself still comes through as usual.
+        let event: SdlEvent = serde_json::from_value(line).unwrap();
+        assert!(matches!(event, SdlEvent::ControllerAdded { which: 2, .. }));
+    }
+
+    #[test]
+    fn skips_device_info_that_isnt_there() {
+        let old: Value = serde_json::from_str(
+            r#"{"ControllerAdded":{"timestamp":5,"which":2}}"#).unwrap();
+        assert!(read_device_info(&old).is_none());
+        let removed: Value = serde_json::from_str(
+            r#"{"ControllerRemoved":{"timestamp":5,"which":2}}"#).unwrap();
+        assert!(read_device_info(&removed).is_none());
+    }
+}
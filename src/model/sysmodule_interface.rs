@@ -143,14 +143,16 @@ impl UdpPacket {
 
 struct SysmoduleUdpWriter {
     ips: Vec<String>,
+    port: u16,
     formatted: Vec<String>,
     writer: UdpSocket
 }
 
 impl SysmoduleUdpWriter {
-    pub fn new(udp: UdpSocket) -> SysmoduleUdpWriter {
+    pub fn new(udp: UdpSocket, port: u16) -> SysmoduleUdpWriter {
         return SysmoduleUdpWriter {
             ips: vec!(),
+            port: port,
             formatted: vec!(),
             writer: udp
         }
@@ -164,7 +166,7 @@ impl SysmoduleUdpWriter {
         self.ips = ips;
         let mut formatted: Vec<String> = vec!();
         for ip in &self.ips {
-            formatted.push(format!("{}:8000", ip));
+            formatted.push(format!("{}:{}", ip, self.port));
         }
         self.formatted = formatted;
     }
@@ -206,13 +208,13 @@ pub struct SysmoduleInterface {
 
 impl SysmoduleInterface {
 
-    pub fn new() -> SysmoduleInterface {
-        let udp: UdpSocket = UdpSocket::bind("0.0.0.0:8000")
-            .expect("Failed to bind UDP socket.");
-        
-        return SysmoduleInterface {
-            udp_writer: SysmoduleUdpWriter::new(udp)
-        }
+    pub fn new(bind: &str, port: u16) -> Result<SysmoduleInterface, String> {
+        let udp: UdpSocket = UdpSocket::bind(bind).map_err(|e| format!(
+            "Failed to bind UDP socket to {}: {}", bind, e))?;
+
+        return Ok(SysmoduleInterface {
+            udp_writer: SysmoduleUdpWriter::new(udp, port)
+        });
     }
 
     pub fn get_ips(&self) -> Vec<String> {